@@ -0,0 +1,108 @@
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::hosts::enums::HostArg;
+use crate::structs::UploadFile;
+
+pub const SIDECAR_EXT: &str = "url";
+
+// What to do with a local file after its uploads.
+#[derive(Clone, Debug)]
+pub enum LocalAction {
+    Move(PathBuf),
+    Delete,
+    Sidecar,
+}
+
+impl LocalAction {
+    pub fn parse(s: &str) -> Result<LocalAction, String> {
+        match s.split_once(':') {
+            Some(("move", dir)) if !dir.is_empty() => Ok(LocalAction::Move(PathBuf::from(dir))),
+            None if s == "delete" => Ok(LocalAction::Delete),
+            None if s == "sidecar" => Ok(LocalAction::Sidecar),
+            _ => Err(format!("invalid action: {}, expected move:<dir>, delete or sidecar", s)),
+        }
+    }
+
+    // Failed files can only be moved aside.
+    pub fn parse_failure(s: &str) -> Result<LocalAction, String> {
+        match LocalAction::parse(s)? {
+            LocalAction::Move(dir) => Ok(LocalAction::Move(dir)),
+            _ => Err(format!("invalid action: {}, expected move:<dir>", s)),
+        }
+    }
+
+    pub fn move_dir(&self) -> Option<&PathBuf> {
+        match self {
+            LocalAction::Move(dir) => Some(dir),
+            _ => None,
+        }
+    }
+}
+
+// Adds " (1)", " (2)" and so on before the extension until the name is free.
+fn get_free_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .unwrap_or(path)
+}
+
+// Renames can't cross filesystems, so fall back to copying.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(())
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
+
+// An Internet Shortcut next to the file, named after the host when the file goes to more than one.
+pub fn write_sidecar(file: &UploadFile, host: &HostArg, host_total: usize, url: &str) -> Result<(), Box<dyn Error>> {
+    let file_name = file.path.file_name().ok_or("path does not have a filename")?.to_string_lossy();
+    let sidecar_name = if host_total > 1 {
+        format!("{}.{}.{}", file_name, host, SIDECAR_EXT)
+    } else {
+        format!("{}.{}", file_name, SIDECAR_EXT)
+    };
+    let sidecar_path = file.path.with_file_name(sidecar_name);
+    fs::write(&sidecar_path, format!("[InternetShortcut]\r\nURL={}\r\n", url))?;
+    Ok(())
+}
+
+// Named like one write_sidecar made, with the file it was made for still next to it. Other .url files are the user's.
+pub fn is_sidecar(path: &Path) -> bool {
+    if path.extension().is_none_or(|ext| ext != SIDECAR_EXT) {
+        return false
+    }
+    let base = path.with_extension("");
+    base.is_file() || (base.extension().is_some() && base.with_extension("").is_file())
+}
+
+// Sidecars are written per upload instead, see write_sidecar.
+pub fn apply(action: &LocalAction, file: &UploadFile) -> Result<(), Box<dyn Error>> {
+    match action {
+        LocalAction::Move(dir) => {
+            // Files found with -d keep their layout under the target dir.
+            let target_dir = dir.join(&file.rel_dir);
+            fs::create_dir_all(&target_dir)?;
+            let file_name = file.path.file_name().ok_or("path does not have a filename")?;
+            let target = get_free_path(target_dir.join(file_name));
+            move_file(&file.path, &target)?;
+            println!("Moved to {}", target.display());
+        },
+        LocalAction::Delete => {
+            fs::remove_file(&file.path)?;
+            println!("Deleted local file.");
+        },
+        LocalAction::Sidecar => {},
+    }
+    Ok(())
+}
@@ -3,11 +3,11 @@ use std::fs::File;
 use std::path::PathBuf;
 use std::error::Error;
 use mime_guess::MimeGuess;
-use reqwest::blocking::{Client, multipart, Response as ReqwestResp};
-use reqwest::Error as ReqwestErr;
-use reqwest::header::{CONTENT_TYPE, COOKIE, HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::blocking::{Body, Client, multipart, Response as ReqwestResp};
+use reqwest::{Error as ReqwestErr, Method};
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, HeaderMap, HeaderValue, USER_AGENT};
 use serde::Serialize;
-use crate::utils;
+use crate::structs::UploadFile;
 use crate::client::structs::ProgressReader;
 
 const CLIENT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/128.0.0.0 Safari/537.36";
@@ -32,6 +32,15 @@ impl UploaderClient {
         Ok(uploader_client)
     }
 
+    // The header RequestBuilder::basic_auth sets, for hosts that pass their auth along with other headers.
+    pub fn basic_auth_header(&self, username: &str, password: &str) -> Result<HeaderValue, Box<dyn Error>> {
+        let req = self.c.get("http://localhost/")
+            .basic_auth(username, Some(password))
+            .build()?;
+        let value = req.headers().get(AUTHORIZATION).ok_or("failed to build basic auth header")?;
+        Ok(value.clone())
+    }
+
     pub fn do_get(&mut self, url: &str, headers: Option<HeaderMap>, cookies: Option<&HashMap<String, String>>) -> Result<ReqwestResp, Box<dyn Error>> {
         let mut req = self.c.get(url);
 
@@ -54,6 +63,23 @@ impl UploaderClient {
         Ok(resp)
     }
 
+    pub fn do_request(&mut self, method: Method, url: &str, headers: Option<HeaderMap>, body: Option<Body>) -> Result<ReqwestResp, Box<dyn Error>> {
+        let mut req = self.c.request(method, url);
+
+        if let Some(h) = headers {
+            req = req.headers(h);
+        }
+
+        if let Some(b) = body {
+            req = req.body(b);
+        }
+
+        let resp = req.send()?;
+        resp.error_for_status_ref()?;
+
+        Ok(resp)
+    }
+
     pub fn do_json_put<T: Serialize>(&mut self, url: &str, headers: Option<HeaderMap>, body: &T) -> Result<ReqwestResp, Box<dyn Error>> {
         let b = serde_json::to_string(body)?;
 
@@ -71,6 +97,25 @@ impl UploaderClient {
         Ok(resp)
     }
 
+    pub fn do_delete<T: Serialize>(&mut self, url: &str, headers: Option<HeaderMap>, body: Option<&T>) -> Result<ReqwestResp, Box<dyn Error>> {
+        let mut req = self.c.delete(url);
+
+        if let Some(h) = headers {
+            req = req.headers(h);
+        }
+
+        if let Some(b) = body {
+            let body = serde_json::to_string(b)?;
+            req = req.body(body)
+                .header(CONTENT_TYPE, "application/json; charset=utf-8")
+        }
+
+        let resp = req.send()?;
+        resp.error_for_status_ref()?;
+
+        Ok(resp)
+    }
+
     pub fn do_post<T: Serialize>(&self, url: &str, headers: Option<HeaderMap>, body: Option<&T>) -> Result<ReqwestResp, Box<dyn Error>> {
         let mut req = self.c.post(url);
 
@@ -92,28 +137,61 @@ impl UploaderClient {
         Ok(resp)
     }
 
+    pub fn do_form_post(&mut self, url: &str, headers: Option<HeaderMap>, fields: &[(String, String)]) -> Result<ReqwestResp, Box<dyn Error>> {
+        let resp = self.do_form_post_unchecked(url, headers, fields)?;
+        resp.error_for_status_ref()?;
+
+        Ok(resp)
+    }
+
+    // For apis that explain their errors in the body, the response is returned whatever its status.
+    pub fn do_form_post_unchecked(&mut self, url: &str, headers: Option<HeaderMap>, fields: &[(String, String)]) -> Result<ReqwestResp, Box<dyn Error>> {
+        let mut req = self.c.post(url)
+            .form(fields);
+
+        if let Some(h) = headers {
+            req = req.headers(h);
+        }
+
+        Ok(req.send()?)
+    }
+
     pub fn multipart_upload(
         &mut self,
         url: &str,
         field_name: String,
-        file_path: &PathBuf,
+        file: &UploadFile,
         headers: Option<HeaderMap>,
         fields: Option<Vec<(String, String)>>,
         cookies: Option<&HashMap<String, String>>
     ) -> Result<ReqwestResp, Box<dyn Error>> {
-        let f = File::open(file_path)?;
+        self.multipart_request(Method::POST, url, field_name, file, headers, fields, cookies)
+    }
+
+    // The part is named after the file's upload name, which --from-file lines can change.
+    #[allow(clippy::too_many_arguments)]
+    pub fn multipart_request(
+        &mut self,
+        method: Method,
+        url: &str,
+        field_name: String,
+        file: &UploadFile,
+        headers: Option<HeaderMap>,
+        fields: Option<Vec<(String, String)>>,
+        cookies: Option<&HashMap<String, String>>
+    ) -> Result<ReqwestResp, Box<dyn Error>> {
+        let f = File::open(&file.path)?;
         let file_size = f.metadata()?.len();
 
         let pr = ProgressReader::new(f, file_size)?;
-        let fname = utils::get_fname_string_from_path(file_path)?;
-        let file_mime = MimeGuess::from_path(fname.as_str())
+        let file_mime = MimeGuess::from_path(file.name.as_str())
             .first_or_octet_stream()
             .to_string();
 
         let mut form = multipart::Form::new()
             .part(field_name, multipart::Part::reader_with_length(pr, file_size)
             .mime_str(&file_mime)?
-            .file_name(fname));
+            .file_name(file.name.clone()));
 
         if let Some(fields) = fields {
             for (name, value) in fields {
@@ -121,7 +199,7 @@ impl UploaderClient {
             }
         }
 
-        let mut req = self.c.post(url)
+        let mut req = self.c.request(method, url)
             .multipart(form);
 
         if let Some(h) = headers {
@@ -142,4 +220,74 @@ impl UploaderClient {
 
         Ok(resp)
     }
+
+    pub fn put_upload(&mut self, url: &str, file_path: &PathBuf, headers: Option<HeaderMap>) -> Result<ReqwestResp, Box<dyn Error>> {
+        self.body_upload(Method::PUT, url, file_path, headers)
+    }
+
+    // Sends the file as the raw request body.
+    pub fn body_upload(&mut self, method: Method, url: &str, file_path: &PathBuf, headers: Option<HeaderMap>) -> Result<ReqwestResp, Box<dyn Error>> {
+        self.file_body_request(method, url, file_path, headers, true)
+    }
+
+    // Like body_upload but without a Content-Length, the body is streamed in chunks.
+    pub fn chunked_upload(&mut self, method: Method, url: &str, file_path: &PathBuf, headers: Option<HeaderMap>) -> Result<ReqwestResp, Box<dyn Error>> {
+        self.file_body_request(method, url, file_path, headers, false)
+    }
+
+    fn file_body_request(&mut self, method: Method, url: &str, file_path: &PathBuf, headers: Option<HeaderMap>, sized: bool) -> Result<ReqwestResp, Box<dyn Error>> {
+        let f = File::open(file_path)?;
+        let file_size = f.metadata()?.len();
+
+        let pr = ProgressReader::new(f, file_size)?;
+        let body = if sized { Body::sized(pr, file_size) } else { Body::new(pr) };
+        let mut req = self.c.request(method, url)
+            .body(body);
+
+        if let Some(h) = headers {
+            req = req.headers(h);
+        }
+
+        let resp = req.send()?;
+        resp.error_for_status_ref()?;
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_server::{self, Response, TestServer};
+    use super::*;
+
+    #[test]
+    fn multipart_uses_upload_name() {
+        let server = TestServer::new(|_| Response::new(200));
+        let file = test_server::upload_file("on-disk.bin", "renamed.txt", b"hello");
+        let fields = vec![("a".to_string(), "b".to_string())];
+
+        UploaderClient::new().unwrap()
+            .multipart_upload(&server.url, "file".to_string(), &file, None, Some(fields), None)
+            .unwrap();
+        let body = server.requests()[0].text();
+        assert!(body.contains("Content-Disposition: form-data; name=\"file\"; filename=\"renamed.txt\"\r\nContent-Type: text/plain\r\n\r\nhello\r\n"));
+        assert!(body.contains("Content-Disposition: form-data; name=\"a\"\r\n\r\nb\r\n"));
+        assert!(!body.contains("on-disk.bin"));
+    }
+
+    #[test]
+    fn body_uploads() {
+        let server = TestServer::new(|_| Response::new(200));
+        let file = test_server::upload_file("a.bin", "a.bin", b"hello");
+        let mut c = UploaderClient::new().unwrap();
+
+        c.put_upload(&server.url, &file.path, None).unwrap();
+        c.chunked_upload(Method::PUT, &server.url, &file.path, None).unwrap();
+        let requests = server.requests();
+        assert_eq!(requests[0].header("Content-Length"), Some("5"));
+        assert_eq!(requests[0].header("Transfer-Encoding"), None);
+        assert_eq!(requests[1].header("Content-Length"), None);
+        assert_eq!(requests[1].header("Transfer-Encoding"), Some("chunked"));
+        assert!(requests.iter().all(|r| r.body == b"hello"));
+    }
 }
@@ -6,19 +6,35 @@ use indicatif::style::TemplateError;
 pub(crate) struct ProgressReader<R> {
     inner: R,
     pb: ProgressBar,
+    finish_on_drop: bool,
+}
+
+pub(crate) fn new_progress_bar(total_size: u64) -> Result<ProgressBar, TemplateError> {
+    let pb = ProgressBar::new(total_size);
+    pb.set_style(ProgressStyle::with_template("[{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% at {binary_bytes_per_sec}, {bytes}/{total_bytes} (ETA: {eta})")?
+        .progress_chars("#>-"));
+    Ok(pb)
 }
 
 impl<R: Read> ProgressReader<R> {
     pub(crate) fn new(inner: R, total_size: u64) -> Result<Self, TemplateError> {
-        let pb = ProgressBar::new(total_size);
-        pb.set_style(ProgressStyle::with_template("[{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% at {binary_bytes_per_sec}, {bytes}/{total_bytes} (ETA: {eta})")?
-            .progress_chars("#>-"));
+        let pb = new_progress_bar(total_size)?;
 
         Ok(ProgressReader {
             inner,
             pb,
+            finish_on_drop: true,
         })
     }
+
+    // For uploads split into several requests sharing one bar, the caller finishes it.
+    pub(crate) fn with_bar(inner: R, pb: ProgressBar) -> Self {
+        ProgressReader {
+            inner,
+            pb,
+            finish_on_drop: false,
+        }
+    }
 }
 
 impl<R: Read> Read for ProgressReader<R> {
@@ -32,6 +48,8 @@ impl<R: Read> Read for ProgressReader<R> {
 
 impl<R> Drop for ProgressReader<R> {
     fn drop(&mut self) {
-        self.pb.finish();
+        if self.finish_on_drop {
+            self.pb.finish();
+        }
     }
-}
\ No newline at end of file
+}
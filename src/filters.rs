@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use globset::{GlobBuilder, GlobMatcher};
+use mime_guess::{mime, MimeGuess};
+use crate::actions::{self, LocalAction};
+use crate::hosts::sizes::Size;
+use crate::structs::Args;
+
+const SCAN_SECTION: &str = "scan";
+
+// What a pattern is matched against, patterns with a slash always use the whole relative path.
+#[derive(Clone, Copy)]
+enum MatchOn {
+    Path,
+    FileName,
+    // Like in .gitignore, so excluding node_modules drops everything under it.
+    AnyComponent,
+}
+
+struct GlobPattern {
+    text: String,
+    matcher: GlobMatcher,
+    match_on: MatchOn,
+    dropped: usize,
+}
+
+impl GlobPattern {
+    fn new(text: &str, slashless: MatchOn) -> Result<GlobPattern, Box<dyn Error>> {
+        let text = text.trim().trim_start_matches("./");
+        let matcher = GlobBuilder::new(text)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("invalid glob pattern \"{}\": {}", text, e))?
+            .compile_matcher();
+
+        let pattern = GlobPattern {
+            text: text.to_string(),
+            matcher,
+            match_on: if text.contains('/') { MatchOn::Path } else { slashless },
+            dropped: 0,
+        };
+        Ok(pattern)
+    }
+
+    fn is_match(&self, rel_path: &Path) -> bool {
+        match self.match_on {
+            MatchOn::Path => self.matcher.is_match(rel_path),
+            MatchOn::FileName => rel_path.file_name().is_some_and(|name| self.matcher.is_match(name)),
+            MatchOn::AnyComponent => rel_path.components().any(|comp| self.matcher.is_match(comp.as_os_str())),
+        }
+    }
+}
+
+// Include/exclude globs for files found under -d, matched against the path relative to its root.
+pub struct GlobFilter {
+    includes: Vec<GlobPattern>,
+    excludes: Vec<GlobPattern>,
+    not_included: usize,
+}
+
+fn parse_patterns(patterns: &[String], slashless: MatchOn) -> Result<Vec<GlobPattern>, Box<dyn Error>> {
+    patterns.iter()
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| GlobPattern::new(p, slashless))
+        .collect()
+}
+
+impl GlobFilter {
+    pub fn new(includes: &[String], excludes: &[String], cfg: &HashMap<String, HashMap<String, String>>) -> Result<GlobFilter, Box<dyn Error>> {
+        let default_excludes: Vec<String> = cfg.get(SCAN_SECTION)
+            .and_then(|section| section.get("default_excludes"))
+            .map(|value| value.split(',').map(|p| p.to_string()).collect())
+            .unwrap_or_default();
+
+        let mut exclude_patterns = parse_patterns(&default_excludes, MatchOn::AnyComponent)?;
+        exclude_patterns.extend(parse_patterns(excludes, MatchOn::AnyComponent)?);
+
+        let filter = GlobFilter {
+            includes: parse_patterns(includes, MatchOn::FileName)?,
+            excludes: exclude_patterns,
+            not_included: 0,
+        };
+        Ok(filter)
+    }
+
+    pub fn keep(&mut self, rel_path: &Path) -> bool {
+        if !self.includes.is_empty() && !self.includes.iter().any(|p| p.is_match(rel_path)) {
+            self.not_included += 1;
+            return false
+        }
+
+        // Only the first matching exclude is counted, so the totals add up.
+        if let Some(pattern) = self.excludes.iter_mut().find(|p| p.is_match(rel_path)) {
+            pattern.dropped += 1;
+            return false
+        }
+        true
+    }
+
+    pub fn print_report(&self) {
+        if self.not_included > 0 {
+            println!("Filtered {} file(s) not matching any include pattern.", self.not_included);
+        }
+        for pattern in self.excludes.iter().filter(|p| p.dropped > 0) {
+            println!("Filtered {} file(s) matching exclude pattern: {}", pattern.dropped, pattern.text);
+        }
+    }
+}
+
+// Durations back from now, e.g. 30m, 12h, 7d, 2w, or a date/time like 2024-10-01 or 2024-10-01T12:00:00Z.
+pub fn parse_time_arg(s: &str) -> Result<SystemTime, String> {
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(SystemTime::from(dt))
+    }
+
+    let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN)));
+    if let Ok(naive) = naive {
+        return Local.from_local_datetime(&naive)
+            .earliest()
+            .map(SystemTime::from)
+            .ok_or_else(|| format!("invalid local time: {}", s))
+    }
+
+    let split_at = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split_at);
+    let secs_per_unit: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid duration or date: {}, e.g. 12h, 7d or 2024-10-01", s)),
+    };
+    let secs = num.parse::<u64>()
+        .map_err(|_| format!("invalid duration: {}", s))?
+        .checked_mul(secs_per_unit)
+        .ok_or_else(|| format!("duration too long: {}", s))?;
+
+    SystemTime::now()
+        .checked_sub(Duration::from_secs(secs))
+        .ok_or_else(|| format!("duration too long: {}", s))
+}
+
+fn mime_matches(pattern: &str, path: &Path) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let (p_type, p_sub) = pattern.split_once('/').unwrap_or((&pattern, "*"));
+
+    let guess = MimeGuess::from_path(path);
+    let mimes = if guess.is_empty() { vec![mime::APPLICATION_OCTET_STREAM] } else { guess.iter().collect() };
+    mimes.iter().any(|m| {
+        (p_type == "*" || p_type == m.type_().as_str()) && (p_sub == "*" || p_sub == m.subtype().as_str())
+    })
+}
+
+#[derive(Default)]
+struct DropCounts {
+    too_small: usize,
+    too_large: usize,
+    too_old: usize,
+    too_new: usize,
+    wrong_mime: usize,
+    sidecars: usize,
+}
+
+// Size, age and type filters applied to every candidate file in filter_paths, which also drop our own sidecars.
+pub struct FileFilter {
+    min_size: Option<usize>,
+    max_size: Option<usize>,
+    newer_than: Option<SystemTime>,
+    older_than: Option<SystemTime>,
+    mimes: Vec<String>,
+    // So a later run doesn't upload the sidecars as well.
+    skip_sidecars: bool,
+    dropped: DropCounts,
+}
+
+impl FileFilter {
+    pub fn new(args: &Args) -> FileFilter {
+        FileFilter {
+            min_size: args.min_size.as_ref().map(Size::value),
+            max_size: args.max_size.as_ref().map(Size::value),
+            newer_than: args.newer_than,
+            older_than: args.older_than,
+            mimes: args.mime.clone(),
+            skip_sidecars: matches!(args.on_success, Some(LocalAction::Sidecar)),
+            dropped: DropCounts::default(),
+        }
+    }
+
+    pub fn keep(&mut self, path: &Path) -> io::Result<bool> {
+        if self.skip_sidecars && actions::is_sidecar(path) {
+            self.dropped.sidecars += 1;
+            return Ok(false)
+        }
+
+        let meta = fs::metadata(path)?;
+        let size = meta.len() as usize;
+
+        if self.min_size.is_some_and(|min| size < min) {
+            self.dropped.too_small += 1;
+            return Ok(false)
+        }
+        if self.max_size.is_some_and(|max| size > max) {
+            self.dropped.too_large += 1;
+            return Ok(false)
+        }
+
+        if self.newer_than.is_some() || self.older_than.is_some() {
+            let mtime = meta.modified()?;
+            if self.newer_than.is_some_and(|t| mtime < t) {
+                self.dropped.too_old += 1;
+                return Ok(false)
+            }
+            if self.older_than.is_some_and(|t| mtime > t) {
+                self.dropped.too_new += 1;
+                return Ok(false)
+            }
+        }
+
+        if !self.mimes.is_empty() && !self.mimes.iter().any(|m| mime_matches(m, path)) {
+            self.dropped.wrong_mime += 1;
+            return Ok(false)
+        }
+        Ok(true)
+    }
+
+    pub fn print_report(&self) {
+        let counts = [
+            (self.dropped.too_small, "smaller than --min-size"),
+            (self.dropped.too_large, "larger than --max-size"),
+            (self.dropped.too_old, "modified before --newer-than"),
+            (self.dropped.too_new, "modified after --older-than"),
+            (self.dropped.wrong_mime, "not matching --mime"),
+            (self.dropped.sidecars, "written by --on-success sidecar"),
+        ];
+        for (count, reason) in counts.iter().filter(|(count, _)| *count > 0) {
+            println!("Filtered {} file(s) {}.", count, reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use crate::test_server;
+    use super::*;
+
+    fn filter(includes: &[&str], excludes: &[&str]) -> GlobFilter {
+        let to_vec = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<String>>();
+        GlobFilter::new(&to_vec(includes), &to_vec(excludes), &HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn includes_match_file_names() {
+        let mut f = filter(&["*.jpg", "notes"], &[]);
+        assert!(f.keep(Path::new("a.jpg")));
+        assert!(f.keep(Path::new("2024/trip/a.jpg")));
+        assert!(f.keep(Path::new("docs/notes")));
+        // A directory name alone doesn't pull in everything under it.
+        assert!(!f.keep(Path::new("notes/todo.txt")));
+        assert!(!f.keep(Path::new("pics.jpg/readme.txt")));
+        assert_eq!(f.not_included, 2);
+
+        let mut f = filter(&["notes/**", "./raw/*.cr2"], &[]);
+        assert!(f.keep(Path::new("notes/todo.txt")));
+        assert!(f.keep(Path::new("raw/a.cr2")));
+        assert!(!f.keep(Path::new("raw/old/a.cr2")));
+        assert!(!f.keep(Path::new("old/notes/todo.txt")));
+    }
+
+    #[test]
+    fn excludes_match_any_component() {
+        let mut f = filter(&[], &[".git", "*.tmp", "raw/**"]);
+        assert!(!f.keep(Path::new(".git/config")));
+        assert!(!f.keep(Path::new("sub/.git/HEAD")));
+        assert!(!f.keep(Path::new("a/b.tmp")));
+        assert!(!f.keep(Path::new("build.tmp/out.bin")));
+        assert!(!f.keep(Path::new("raw/a.cr2")));
+        assert!(f.keep(Path::new("old/raw/a.cr2")));
+        assert!(f.keep(Path::new("a.git")));
+        assert_eq!(f.excludes.iter().map(|p| p.dropped).collect::<Vec<usize>>(), [2, 2, 1]);
+    }
+
+    #[test]
+    fn excludes_apply_after_includes() {
+        let mut f = filter(&["*.mkv"], &["samples"]);
+        assert!(f.keep(Path::new("show/ep1.mkv")));
+        assert!(!f.keep(Path::new("show/samples/ep1.mkv")));
+        assert!(!f.keep(Path::new("show/ep1.srt")));
+    }
+
+    fn secs_ago(s: &str) -> u64 {
+        let time = parse_time_arg(s).unwrap();
+        SystemTime::now().duration_since(time).unwrap().as_secs()
+    }
+
+    #[test]
+    fn durations() {
+        for (s, secs) in [("45s", 45), ("30m", 30 * 60), ("12h", 12 * 3600), ("7d", 7 * 86400), ("2w", 14 * 86400), ("0s", 0)] {
+            let ago = secs_ago(s);
+            assert!(ago >= secs && ago <= secs + 5, "{} was {}s ago", s, ago);
+        }
+    }
+
+    #[test]
+    fn dates() {
+        let utc = parse_time_arg("2024-10-01T12:00:00Z").unwrap();
+        assert_eq!(utc, SystemTime::UNIX_EPOCH + Duration::from_secs(1_727_784_000));
+        let offset = parse_time_arg("2024-10-01T14:00:00+02:00").unwrap();
+        assert_eq!(offset, utc);
+
+        let local = Local.with_ymd_and_hms(2024, 10, 1, 0, 0, 0).earliest().unwrap();
+        assert_eq!(parse_time_arg("2024-10-01").unwrap(), SystemTime::from(local));
+        let local = Local.with_ymd_and_hms(2024, 10, 1, 12, 30, 0).earliest().unwrap();
+        assert_eq!(parse_time_arg("2024-10-01T12:30:00").unwrap(), SystemTime::from(local));
+    }
+
+    #[test]
+    fn invalid_times() {
+        for s in ["", "12", "12x", "h", "-5m", "1.5h", "2024-13-01", "yesterday", "99999999999999999w", "18446744073709551615s"] {
+            assert!(parse_time_arg(s).is_err(), "{} should be rejected", s);
+        }
+    }
+
+    #[test]
+    fn sidecars() {
+        let dir = test_server::temp_dir();
+        for name in ["a.bin", "a.bin.url", "a.bin.gofile.url", "orphan.bin.url", "bookmark.url", "b.txt.catbox.url"] {
+            fs::write(dir.join(name), "x").unwrap();
+        }
+        fs::write(dir.join("b.txt"), "x").unwrap();
+
+        let args = Args::try_parse_from(["uploader-rs", "--hosts", "gofile", "--on-success", "sidecar"]).unwrap();
+        let mut f = FileFilter::new(&args);
+        let kept: Vec<&str> = ["a.bin", "a.bin.url", "a.bin.gofile.url", "orphan.bin.url", "bookmark.url", "b.txt", "b.txt.catbox.url"]
+            .into_iter()
+            .filter(|name| f.keep(&dir.join(name)).unwrap())
+            .collect();
+        assert_eq!(kept, ["a.bin", "orphan.bin.url", "bookmark.url", "b.txt"]);
+        assert_eq!(f.dropped.sidecars, 3);
+
+        // Without sidecars being written, .url files are like any other.
+        let args = Args::try_parse_from(["uploader-rs", "--hosts", "gofile"]).unwrap();
+        assert!(FileFilter::new(&args).keep(&dir.join("a.bin.url")).unwrap());
+    }
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use crate::structs::UploadRecord;
+use crate::utils;
+
+const HISTORY_FILENAME: &str = "history.jsonl";
+
+pub fn get_history_path() -> Result<PathBuf, Box<dyn Error>> {
+    let exe_path = utils::get_exe_path()?;
+    Ok(exe_path.join(HISTORY_FILENAME))
+}
+
+pub fn append_record(path: &PathBuf, record: &UploadRecord) -> Result<(), Box<dyn Error>> {
+    let mut data = serde_json::to_vec(record)?;
+    data.push(b'\n');
+
+    let mut f = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    f.write_all(&data)?;
+
+    Ok(())
+}
+
+pub fn read_records(path: &PathBuf) -> Result<Vec<UploadRecord>, Box<dyn Error>> {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut records: Vec<UploadRecord> = Vec::new();
+    for line in data.lines().filter(|l| !l.trim().is_empty()) {
+        records.push(serde_json::from_str(line)?);
+    }
+    Ok(records)
+}
+
+pub fn write_records(path: &PathBuf, records: &[UploadRecord]) -> Result<(), Box<dyn Error>> {
+    let mut data: Vec<u8> = Vec::new();
+    for record in records {
+        data.extend(serde_json::to_vec(record)?);
+        data.push(b'\n');
+    }
+
+    fs::write(path, data)?;
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{self, Stdio};
+use serde::Serialize;
+use crate::hosts::enums::HostArg;
+use crate::structs::{BatchSummary, UploadFile, UploadRecord};
+
+const HOOKS_SECTION: &str = "hooks";
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum HookData<'a> {
+    Upload(&'a UploadRecord),
+    File {
+        host: String,
+        filename: &'a str,
+        file_path: &'a Path,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Batch(&'a BatchSummary),
+}
+
+#[derive(Serialize)]
+struct HookPayload<'a> {
+    event: &'a str,
+    #[serde(flatten)]
+    data: HookData<'a>,
+}
+
+// Shell commands from the [hooks] section. They get the upload as UPLOADER_* env vars and as JSON on stdin.
+pub struct Hooks {
+    commands: HashMap<String, String>,
+}
+
+fn make_command(cmd: &str) -> process::Command {
+    if cfg!(windows) {
+        let mut command = process::Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    } else {
+        let mut command = process::Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    }
+}
+
+impl Hooks {
+    pub fn new(cfg: &HashMap<String, HashMap<String, String>>) -> Hooks {
+        let commands = cfg.get(HOOKS_SECTION)
+            .map(|section| section.iter()
+                .filter(|(_, cmd)| !cmd.trim().is_empty())
+                .map(|(event, cmd)| (event.clone(), cmd.clone()))
+                .collect())
+            .unwrap_or_default();
+        Hooks { commands }
+    }
+
+    // Ok(None) if there's no command for the event, otherwise whether it exited with 0.
+    fn run(&self, payload: &HookPayload, env: &[(&str, String)]) -> Result<Option<bool>, Box<dyn Error>> {
+        let Some(cmd) = self.commands.get(payload.event) else {
+            return Ok(None)
+        };
+
+        let mut child = make_command(cmd)
+            .env("UPLOADER_EVENT", payload.event)
+            .envs(env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        let data = serde_json::to_vec(payload)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // Hooks that don't read stdin may exit before it's written.
+            match stdin.write_all(&data) {
+                Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err.into()),
+                _ => {},
+            }
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            println!("Hook {} exited with {}.", payload.event, status);
+        }
+        Ok(Some(status.success()))
+    }
+
+    fn run_or_print(&self, payload: &HookPayload, env: &[(&str, String)]) -> Option<bool> {
+        match self.run(payload, env) {
+            Ok(success) => success,
+            Err(err) => {
+                println!("Hook {} failed to run.\n{:?}", payload.event, err);
+                Some(false)
+            },
+        }
+    }
+
+    fn run_file_event(&self, event: &str, file: &UploadFile, host: &HostArg, error: Option<String>) -> Option<bool> {
+        let mut env = vec![
+            ("UPLOADER_FILE_PATH", file.path.to_string_lossy().into_owned()),
+            ("UPLOADER_FILENAME", file.name.clone()),
+            ("UPLOADER_HOST", host.to_string()),
+        ];
+        if let Some(error) = &error {
+            env.push(("UPLOADER_ERROR", error.clone()));
+        }
+
+        let payload = HookPayload {
+            event,
+            data: HookData::File {
+                host: host.to_string(),
+                filename: &file.name,
+                file_path: &file.path,
+                error,
+            },
+        };
+        self.run_or_print(&payload, &env)
+    }
+
+    // Whether to go ahead with the upload, a failing pre_upload hook skips the file for this host.
+    pub fn pre_upload(&self, file: &UploadFile, host: &HostArg) -> bool {
+        self.run_file_event("pre_upload", file, host, None).unwrap_or(true)
+    }
+
+    pub fn post_upload(&self, record: &UploadRecord) {
+        let env = [
+            ("UPLOADER_FILE_PATH", record.file_path.to_string_lossy().into_owned()),
+            ("UPLOADER_FILENAME", record.filename.clone()),
+            ("UPLOADER_HOST", record.host.clone()),
+            ("UPLOADER_URL", record.result.url.clone()),
+        ];
+        let payload = HookPayload {
+            event: "post_upload",
+            data: HookData::Upload(record),
+        };
+        self.run_or_print(&payload, &env);
+    }
+
+    pub fn on_failure(&self, file: &UploadFile, host: &HostArg, err: &dyn Error) {
+        self.run_file_event("on_failure", file, host, Some(err.to_string()));
+    }
+
+    pub fn on_batch_done(&self, summary: &BatchSummary) {
+        let env = [
+            ("UPLOADER_UPLOADED", summary.uploaded.to_string()),
+            ("UPLOADER_FAILED", summary.failed.to_string()),
+            ("UPLOADER_SKIPPED", summary.skipped.to_string()),
+        ];
+        let payload = HookPayload {
+            event: "on_batch_done",
+            data: HookData::Batch(summary),
+        };
+        self.run_or_print(&payload, &env);
+    }
+}
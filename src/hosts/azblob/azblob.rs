@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use indicatif::ProgressBar;
+use mime_guess::MimeGuess;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::blocking::Body;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
+use reqwest::{Method, StatusCode};
+use url::Url;
+use crate::client::structs::{new_progress_bar, ProgressReader};
+use crate::client::UploaderClient;
+use crate::hosts::azblob::sharedkey::{self, SharedKey};
+use crate::hosts::errors::InvalidAPIKeyError;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const HOST: &str = "azblob";
+const DEFAULT_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_BLOCK_SIZE: u64 = 4000 * 1024 * 1024;
+const MAX_BLOCKS: u64 = 50_000;
+// Single Put Blob requests are capped at 5000 MiB.
+const MAX_PUT_BLOB_SIZE: u64 = 5000 * 1024 * 1024;
+const PATH_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'/');
+
+struct AzConfig {
+    endpoint: Url,
+    container: String,
+    name_prefix: String,
+    block_size: u64,
+    block_threshold: u64,
+    sas_token: String,
+    shared_key: Option<SharedKey>,
+}
+
+fn get_config_num(az_cfg: &HashMap<String, String>, key: &str, default: u64) -> Result<u64, Box<dyn Error>> {
+    let value = utils::get_config_val(az_cfg, key, false)?;
+    if value.is_empty() {
+        return Ok(default)
+    }
+    value.parse::<u64>()
+        .map_err(|_| format!("invalid {} value: {}", key, value).into())
+}
+
+fn load_config(cfg: &HashMap<String, HashMap<String, String>>) -> Result<AzConfig, Box<dyn Error>> {
+    let az_cfg = utils::get_section_or_empty(cfg, HOST);
+
+    let account = utils::get_config_val(&az_cfg, "account", true)?;
+    let mut endpoint = utils::get_config_val(&az_cfg, "endpoint", false)?;
+    if endpoint.is_empty() {
+        endpoint = format!("https://{}.blob.core.windows.net", account);
+    }
+
+    let sas_token = utils::get_config_val(&az_cfg, "sas_token", false)?;
+    let account_key = utils::get_config_val(&az_cfg, "account_key", false)?;
+    let shared_key = if !sas_token.is_empty() {
+        None
+    } else if !account_key.is_empty() {
+        Some(SharedKey {
+            account,
+            key: BASE64_STANDARD.decode(account_key.trim())?,
+        })
+    } else {
+        return Err("azblob needs either sas_token or account_key".into())
+    };
+
+    let block_size = get_config_num(&az_cfg, "block_size", DEFAULT_BLOCK_SIZE)?;
+    if block_size == 0 || block_size > MAX_BLOCK_SIZE {
+        return Err(format!("block_size must be between 1 and {} bytes", MAX_BLOCK_SIZE).into())
+    }
+    let block_threshold = get_config_num(&az_cfg, "block_threshold", block_size)?;
+
+    let az_config = AzConfig {
+        endpoint: Url::parse(&endpoint)?,
+        container: utils::get_config_val(&az_cfg, "container", true)?,
+        name_prefix: utils::get_config_val(&az_cfg, "name_prefix", false)?,
+        block_size,
+        block_threshold: block_threshold.min(MAX_PUT_BLOB_SIZE),
+        sas_token: sas_token.trim_start_matches('?').to_string(),
+        shared_key,
+    };
+    Ok(az_config)
+}
+
+fn blob_url(az_config: &AzConfig, name: &str) -> Result<Url, Box<dyn Error>> {
+    let base = az_config.endpoint.as_str().trim_end_matches('/');
+    let encoded_name = utf8_percent_encode(name, PATH_ENCODE_SET);
+    Ok(Url::parse(&format!("{}/{}/{}", base, az_config.container, encoded_name))?)
+}
+
+// The SAS token goes in front of the operation's own params.
+fn request_url(az_config: &AzConfig, url: &Url, params: &[(&str, &str)]) -> Url {
+    let mut url = url.clone();
+    if !az_config.sas_token.is_empty() {
+        url.set_query(Some(&az_config.sas_token));
+    }
+    if !params.is_empty() {
+        url.query_pairs_mut().extend_pairs(params);
+    }
+    url
+}
+
+fn send(c: &mut UploaderClient, az_config: &AzConfig, method: Method, url: &Url, mut headers: HeaderMap, body: Option<Body>, content_length: u64) -> Result<(), Box<dyn Error>> {
+    sharedkey::sign_request(method.as_str(), url, &mut headers, content_length, az_config.shared_key.as_ref())?;
+
+    if let Err(err) = c.do_request(method, url.as_str(), Some(headers), body) {
+        if let Some(StatusCode::FORBIDDEN) = utils::get_error_status(err.as_ref()) {
+            return Err(InvalidAPIKeyError.into())
+        }
+        return Err(err)
+    }
+    Ok(())
+}
+
+fn get_mime(name: &str) -> Result<HeaderValue, Box<dyn Error>> {
+    let mime = MimeGuess::from_path(name).first_or_octet_stream().to_string();
+    Ok(HeaderValue::from_str(&mime)?)
+}
+
+fn put_blob(c: &mut UploaderClient, az_config: &AzConfig, url: &Url, file_path: &PathBuf, file_size: u64) -> Result<(), Box<dyn Error>> {
+    let pr = ProgressReader::new(File::open(file_path)?, file_size)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-ms-blob-type", HeaderValue::from_static("BlockBlob"));
+    headers.insert(CONTENT_TYPE, get_mime(url.path())?);
+
+    let url = request_url(az_config, url, &[]);
+    send(c, az_config, Method::PUT, &url, headers, Some(Body::sized(pr, file_size)), file_size)
+}
+
+// Block ids have to be the same length within a blob.
+fn make_block_id(block_number: u64) -> String {
+    BASE64_STANDARD.encode(format!("{:08}", block_number))
+}
+
+fn put_blocks(c: &mut UploaderClient, az_config: &AzConfig, url: &Url, file_path: &PathBuf, file_size: u64, pb: &ProgressBar) -> Result<Vec<String>, Box<dyn Error>> {
+    // Blocks are capped at 50,000, so huge files get bigger blocks.
+    let block_size = az_config.block_size.max(file_size.div_ceil(MAX_BLOCKS));
+    let block_total = file_size.div_ceil(block_size);
+
+    let mut block_ids: Vec<String> = Vec::new();
+    for block_number in 0..block_total {
+        let offset = block_number * block_size;
+        let len = block_size.min(file_size - offset);
+
+        let mut f = File::open(file_path)?;
+        f.seek(SeekFrom::Start(offset))?;
+        let pr = ProgressReader::with_bar(f.take(len), pb.clone());
+
+        let block_id = make_block_id(block_number);
+        let block_url = request_url(az_config, url, &[("comp", "block"), ("blockid", &block_id)]);
+        send(c, az_config, Method::PUT, &block_url, HeaderMap::new(), Some(Body::sized(pr, len)), len)?;
+        block_ids.push(block_id);
+    }
+    Ok(block_ids)
+}
+
+fn put_block_list(c: &mut UploaderClient, az_config: &AzConfig, url: &Url, block_ids: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut body = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>");
+    for block_id in block_ids {
+        body.push_str(&format!("<Latest>{}</Latest>", block_id));
+    }
+    body.push_str("</BlockList>");
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/xml"));
+    headers.insert("x-ms-blob-content-type", get_mime(url.path())?);
+
+    let content_length = body.len() as u64;
+    let url = request_url(az_config, url, &[("comp", "blocklist")]);
+    send(c, az_config, Method::PUT, &url, headers, Some(Body::from(body)), content_length)
+}
+
+// Staged blocks that never make it into a block list are dropped by Azure after a week.
+fn put_block_blob(c: &mut UploaderClient, az_config: &AzConfig, url: &Url, file_path: &PathBuf, file_size: u64) -> Result<(), Box<dyn Error>> {
+    let pb = new_progress_bar(file_size)?;
+    let block_ids = put_blocks(c, az_config, url, file_path, file_size, &pb);
+    pb.finish();
+
+    put_block_list(c, az_config, url, &block_ids?)
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, _s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let az_config = load_config(cfg)?;
+    let name = format!("{}{}", utils::render_date_vars(&az_config.name_prefix), file.name);
+    let url = blob_url(&az_config, &name)?;
+
+    let file_size = file.size as u64;
+    if file_size > az_config.block_threshold {
+        put_block_blob(c, &az_config, &url, &file.path, file_size)?;
+    } else {
+        put_blob(c, &az_config, &url, &file.path, file_size)?;
+    }
+
+    let mut result = UploadResult::new(url.to_string());
+    // Needed to delete the blob, delete only matches these through the URL or --key.
+    result.management.insert("container".to_string(), az_config.container);
+    result.management.insert("blob".to_string(), name);
+    Ok(result)
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let blob = result.management.get("blob").ok_or("upload has no azure blob name")?;
+    let container = result.management.get("container").ok_or("upload has no azure container")?;
+
+    let mut az_config = load_config(cfg)?;
+    az_config.container = container.to_string();
+    let url = request_url(&az_config, &blob_url(&az_config, blob)?, &[]);
+
+    send(c, &az_config, Method::DELETE, &url, HeaderMap::new(), None, 0)
+}
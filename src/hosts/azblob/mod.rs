@@ -0,0 +1,5 @@
+#[allow(clippy::module_inception)]
+pub mod azblob;
+mod sharedkey;
+
+pub use azblob::{delete, run};
@@ -0,0 +1,512 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use indicatif::ProgressBar;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::blocking::Body;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
+use reqwest::{Method, StatusCode};
+use sha1::{Digest, Sha1};
+use crate::client::structs::{new_progress_bar, ProgressReader};
+use crate::client::UploaderClient;
+use crate::hosts::b2::structs::*;
+use crate::hosts::errors::InvalidAPIKeyError;
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const HOST: &str = "b2";
+const DEFAULT_API_URL: &str = "https://api.backblazeb2.com";
+const MAX_FILE_SIZE: usize = 10_000_000_000_000;
+const MAX_PARTS: u64 = 10_000;
+// Lets B2 pick the content type from the file extension.
+const AUTO_CONTENT_TYPE: &str = "b2/x-auto";
+const NAME_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'/');
+
+// Account info from b2_authorize_account, kept in the session so each run only authorizes once.
+struct B2Account {
+    auth_token: String,
+    api_url: String,
+    download_url: String,
+    bucket: String,
+    bucket_id: String,
+    part_size: u64,
+    min_part_size: u64,
+}
+
+fn get_config_num(b2_cfg: &HashMap<String, String>, key: &str, default: u64) -> Result<u64, Box<dyn Error>> {
+    let value = utils::get_config_val(b2_cfg, key, false)?;
+    if value.is_empty() {
+        return Ok(default)
+    }
+    value.parse::<u64>()
+        .map_err(|_| format!("invalid {} value: {}", key, value).into())
+}
+
+fn make_auth_headers(token: &str) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    let mut value = HeaderValue::from_str(token)?;
+    value.set_sensitive(true);
+    headers.insert(AUTHORIZATION, value);
+    Ok(headers)
+}
+
+fn api_call_url(api_url: &str, call: &str) -> String {
+    format!("{}/b2api/v2/{}", api_url.trim_end_matches('/'), call)
+}
+
+fn find_bucket_id(c: &mut UploaderClient, auth: &AuthorizeResp, bucket: &str) -> Result<String, Box<dyn Error>> {
+    // Keys restricted to one bucket already say which.
+    if auth.allowed.bucket_name.as_deref() == Some(bucket) {
+        if let Some(bucket_id) = &auth.allowed.bucket_id {
+            return Ok(bucket_id.to_string())
+        }
+    }
+
+    let list_buckets_data = ListBucketsPost {
+        account_id: auth.account_id.clone(),
+        bucket_name: bucket.to_string(),
+    };
+    let url = api_call_url(&auth.api_url, "b2_list_buckets");
+    let resp = c.do_post(&url, Some(make_auth_headers(&auth.authorization_token)?), Some(&list_buckets_data))?;
+    let json_obj: ListBucketsResp = resp.json()?;
+    let bucket_obj = json_obj.buckets.into_iter().next()
+        .ok_or_else(|| format!("b2 bucket not found: {}", bucket))?;
+    Ok(bucket_obj.bucket_id)
+}
+
+fn authorize(c: &mut UploaderClient, b2_cfg: &HashMap<String, String>) -> Result<B2Account, Box<dyn Error>> {
+    let key_id = utils::get_config_val(b2_cfg, "key_id", true)?;
+    let application_key = utils::get_config_val(b2_cfg, "application_key", true)?;
+    let bucket = utils::get_config_val(b2_cfg, "bucket", true)?;
+    let mut api_url = utils::get_config_val(b2_cfg, "api_url", false)?;
+    if api_url.is_empty() {
+        api_url = DEFAULT_API_URL.to_string();
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, c.basic_auth_header(&key_id, &application_key)?);
+
+    let url = api_call_url(&api_url, "b2_authorize_account");
+    let resp = match c.do_get(&url, Some(headers), None) {
+        Ok(resp) => resp,
+        Err(err) => {
+            if let Some(StatusCode::UNAUTHORIZED) = utils::get_error_status(err.as_ref()) {
+                return Err(InvalidAPIKeyError.into())
+            }
+            return Err(err)
+        },
+    };
+    let auth: AuthorizeResp = resp.json()?;
+    let bucket_id = find_bucket_id(c, &auth, &bucket)?;
+
+    Ok(B2Account {
+        auth_token: auth.authorization_token,
+        api_url: auth.api_url,
+        download_url: auth.download_url,
+        bucket,
+        bucket_id,
+        part_size: auth.recommended_part_size,
+        min_part_size: auth.absolute_minimum_part_size,
+    })
+}
+
+fn load_account(c: &mut UploaderClient, b2_cfg: &HashMap<String, String>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<B2Account, Box<dyn Error>> {
+    let auth_token = utils::get_session_val(s, HOST, "auth_token");
+    if !auth_token.is_empty() {
+        return Ok(B2Account {
+            auth_token,
+            api_url: utils::get_session_val(s, HOST, "api_url"),
+            download_url: utils::get_session_val(s, HOST, "download_url"),
+            bucket: utils::get_session_val(s, HOST, "bucket"),
+            bucket_id: utils::get_session_val(s, HOST, "bucket_id"),
+            part_size: utils::get_session_val(s, HOST, "part_size").parse()?,
+            min_part_size: utils::get_session_val(s, HOST, "min_part_size").parse()?,
+        })
+    }
+
+    let account = authorize(c, b2_cfg)?;
+    utils::set_session_val(s, HOST, "auth_token", &account.auth_token);
+    utils::set_session_val(s, HOST, "api_url", &account.api_url);
+    utils::set_session_val(s, HOST, "download_url", &account.download_url);
+    utils::set_session_val(s, HOST, "bucket", &account.bucket);
+    utils::set_session_val(s, HOST, "bucket_id", &account.bucket_id);
+    utils::set_session_val(s, HOST, "part_size", &account.part_size.to_string());
+    utils::set_session_val(s, HOST, "min_part_size", &account.min_part_size.to_string());
+    Ok(account)
+}
+
+fn sha1_hex(reader: &mut impl Read) -> Result<String, io::Error> {
+    let mut hasher = Sha1::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn open_range(file_path: &PathBuf, offset: u64, len: u64) -> Result<io::Take<File>, io::Error> {
+    let mut f = File::open(file_path)?;
+    f.seek(SeekFrom::Start(offset))?;
+    Ok(f.take(len))
+}
+
+fn make_upload_headers(upload_token: &str, sha1: &str) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = make_auth_headers(upload_token)?;
+    headers.insert("X-Bz-Content-Sha1", HeaderValue::from_str(sha1)?);
+    Ok(headers)
+}
+
+fn get_upload_url(c: &mut UploaderClient, account: &B2Account, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadUrlResp, Box<dyn Error>> {
+    let upload_url = utils::get_session_val(s, HOST, "upload_url");
+    if !upload_url.is_empty() {
+        return Ok(UploadUrlResp {
+            upload_url,
+            authorization_token: utils::get_session_val(s, HOST, "upload_token"),
+        })
+    }
+
+    let get_upload_url_data = GetUploadUrlPost {
+        bucket_id: account.bucket_id.clone(),
+    };
+    let url = api_call_url(&account.api_url, "b2_get_upload_url");
+    let resp = c.do_post(&url, Some(make_auth_headers(&account.auth_token)?), Some(&get_upload_url_data))?;
+    let json_obj: UploadUrlResp = resp.json()?;
+
+    utils::set_session_val(s, HOST, "upload_url", &json_obj.upload_url);
+    utils::set_session_val(s, HOST, "upload_token", &json_obj.authorization_token);
+    Ok(json_obj)
+}
+
+fn upload_file(c: &mut UploaderClient, account: &B2Account, file_name: &str, file_path: &PathBuf, file_size: u64, s: &mut HashMap<String, HashMap<String, String>>) -> Result<String, Box<dyn Error>> {
+    let sha1 = sha1_hex(&mut File::open(file_path)?)?;
+    let upload_url = get_upload_url(c, account, s)?;
+
+    let mut headers = make_upload_headers(&upload_url.authorization_token, &sha1)?;
+    headers.insert("X-Bz-File-Name", HeaderValue::from_str(&utf8_percent_encode(file_name, NAME_ENCODE_SET).to_string())?);
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(AUTO_CONTENT_TYPE));
+
+    let pr = ProgressReader::new(File::open(file_path)?, file_size)?;
+    let resp = match c.do_request(Method::POST, &upload_url.upload_url, Some(headers), Some(Body::sized(pr, file_size))) {
+        Ok(resp) => resp,
+        Err(err) => {
+            // Upload urls can expire or get busy, the next file asks for a fresh one.
+            utils::set_session_val(s, HOST, "upload_url", "");
+            return Err(err)
+        },
+    };
+    let json_obj: FileResp = resp.json()?;
+    Ok(json_obj.file_id)
+}
+
+fn start_large_file(c: &mut UploaderClient, account: &B2Account, file_name: &str) -> Result<String, Box<dyn Error>> {
+    let start_large_file_data = StartLargeFilePost {
+        bucket_id: account.bucket_id.clone(),
+        file_name: file_name.to_string(),
+        content_type: AUTO_CONTENT_TYPE.to_string(),
+    };
+    let url = api_call_url(&account.api_url, "b2_start_large_file");
+    let resp = c.do_post(&url, Some(make_auth_headers(&account.auth_token)?), Some(&start_large_file_data))?;
+    let json_obj: FileResp = resp.json()?;
+    Ok(json_obj.file_id)
+}
+
+fn upload_parts(c: &mut UploaderClient, account: &B2Account, file_id: &str, file_path: &PathBuf, file_size: u64, part_size: u64, pb: &ProgressBar) -> Result<Vec<String>, Box<dyn Error>> {
+    let get_upload_part_url_data = FileIdPost {
+        file_id: file_id.to_string(),
+    };
+    let url = api_call_url(&account.api_url, "b2_get_upload_part_url");
+    let resp = c.do_post(&url, Some(make_auth_headers(&account.auth_token)?), Some(&get_upload_part_url_data))?;
+    let upload_url: UploadUrlResp = resp.json()?;
+
+    // Parts are capped at 10,000, so huge files get bigger parts.
+    let part_size = part_size.max(file_size.div_ceil(MAX_PARTS));
+    let part_total = file_size.div_ceil(part_size);
+
+    let mut part_sha1s: Vec<String> = Vec::new();
+    for part_number in 1..=part_total {
+        let offset = (part_number - 1) * part_size;
+        let len = part_size.min(file_size - offset);
+
+        let sha1 = sha1_hex(&mut open_range(file_path, offset, len)?)?;
+        let mut headers = make_upload_headers(&upload_url.authorization_token, &sha1)?;
+        headers.insert("X-Bz-Part-Number", HeaderValue::from(part_number));
+
+        let pr = ProgressReader::with_bar(open_range(file_path, offset, len)?, pb.clone());
+        c.do_request(Method::POST, &upload_url.upload_url, Some(headers), Some(Body::sized(pr, len)))?;
+        part_sha1s.push(sha1);
+    }
+    Ok(part_sha1s)
+}
+
+fn finish_large_file(c: &mut UploaderClient, account: &B2Account, file_id: &str, part_sha1s: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let finish_large_file_data = FinishLargeFilePost {
+        file_id: file_id.to_string(),
+        part_sha1_array: part_sha1s,
+    };
+    let url = api_call_url(&account.api_url, "b2_finish_large_file");
+    c.do_post(&url, Some(make_auth_headers(&account.auth_token)?), Some(&finish_large_file_data))?;
+    Ok(())
+}
+
+fn cancel_large_file(c: &mut UploaderClient, account: &B2Account, file_id: &str) -> Result<(), Box<dyn Error>> {
+    let cancel_large_file_data = FileIdPost {
+        file_id: file_id.to_string(),
+    };
+    let url = api_call_url(&account.api_url, "b2_cancel_large_file");
+    c.do_post(&url, Some(make_auth_headers(&account.auth_token)?), Some(&cancel_large_file_data))?;
+    Ok(())
+}
+
+fn upload_large_file(c: &mut UploaderClient, account: &B2Account, file_name: &str, file_path: &PathBuf, file_size: u64, part_size: u64) -> Result<String, Box<dyn Error>> {
+    let file_id = start_large_file(c, account, file_name)?;
+
+    let pb = new_progress_bar(file_size)?;
+    let parts = upload_parts(c, account, &file_id, file_path, file_size, part_size, &pb);
+    pb.finish();
+
+    let res = parts.and_then(|part_sha1s| finish_large_file(c, account, &file_id, part_sha1s));
+    if let Err(err) = res {
+        if let Err(cancel_err) = cancel_large_file(c, account, &file_id) {
+            println!("Failed to cancel large file upload.\n{:?}", cancel_err);
+        }
+        return Err(err)
+    }
+    Ok(file_id)
+}
+
+fn upload(c: &mut UploaderClient, file: &UploadFile, b2_cfg: &HashMap<String, String>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let account = load_account(c, b2_cfg, s)?;
+
+    let part_size = get_config_num(b2_cfg, "part_size", account.part_size)?;
+    if part_size < account.min_part_size {
+        return Err(format!("part_size must be at least {} bytes", account.min_part_size).into())
+    }
+    let large_file_threshold = get_config_num(b2_cfg, "large_file_threshold", part_size)?;
+
+    let name_prefix = utils::get_config_val(b2_cfg, "name_prefix", false)?;
+    let file_name = format!("{}{}", utils::render_date_vars(&name_prefix), file.name);
+
+    let file_size = file.size as u64;
+    let file_id = if file_size > large_file_threshold {
+        upload_large_file(c, &account, &file_name, &file.path, file_size, part_size)?
+    } else {
+        upload_file(c, &account, &file_name, &file.path, file_size, s)?
+    };
+
+    let url = format!("{}/file/{}/{}",
+        account.download_url.trim_end_matches('/'),
+        account.bucket,
+        utf8_percent_encode(&file_name, NAME_ENCODE_SET));
+
+    let mut result = UploadResult::new(url);
+    result.management.insert("file_id".to_string(), file_id);
+    result.management.insert("file_name".to_string(), file_name);
+    Ok(result)
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    utils::check_file_size_limit(file.size, Size::Custom(MAX_FILE_SIZE))?;
+
+    let b2_cfg = utils::get_section_or_empty(cfg, HOST);
+    let had_session = !utils::get_session_val(s, HOST, "auth_token").is_empty();
+    match upload(c, file, &b2_cfg, s) {
+        // Account tokens last 24 hours, watch mode keeps the session for longer than that.
+        Err(err) if had_session && utils::get_error_status(err.as_ref()) == Some(StatusCode::UNAUTHORIZED) => {
+            println!("B2 authorization expired, authorizing again.");
+            s.remove(HOST);
+            upload(c, file, &b2_cfg, s)
+        },
+        res => res,
+    }
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let file_id = result.management.get("file_id").ok_or("upload has no b2 file id")?;
+    let file_name = result.management.get("file_name").ok_or("upload has no b2 file name")?;
+
+    let b2_cfg = utils::get_section_or_empty(cfg, HOST);
+    let account = authorize(c, &b2_cfg)?;
+
+    let delete_file_version_data = DeleteFileVersionPost {
+        file_name: file_name.to_string(),
+        file_id: file_id.to_string(),
+    };
+    let url = api_call_url(&account.api_url, "b2_delete_file_version");
+    c.do_post(&url, Some(make_auth_headers(&account.auth_token)?), Some(&delete_file_version_data))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use serde_json::json;
+    use crate::test_server::{self, Request, Response, TestServer};
+    use super::*;
+
+    const STALE_TOKEN: &str = "stale-token";
+
+    // A stand-in for the B2 api. Every authorization hands out a new account token, the stale one is expired.
+    fn b2_server(fail_part: Option<u64>) -> TestServer {
+        let authorizations = AtomicUsize::new(0);
+        TestServer::new(move |req: &Request| {
+            let base = req.base_url();
+            let auth = req.header("Authorization").unwrap_or_default();
+            if auth == STALE_TOKEN {
+                return Response::json(401, json!({"status": 401, "code": "expired_auth_token", "message": "Authorization token has expired"}))
+            }
+
+            let call = req.path.rsplit('/').next().unwrap();
+            match call {
+                "b2_authorize_account" => {
+                    let n = authorizations.fetch_add(1, Ordering::SeqCst) + 1;
+                    Response::json(200, json!({
+                        "accountId": "acct",
+                        "authorizationToken": format!("account-token-{}", n),
+                        "apiUrl": base,
+                        "downloadUrl": base,
+                        "recommendedPartSize": 100,
+                        "absoluteMinimumPartSize": 5,
+                        "allowed": {"bucketId": null, "bucketName": null},
+                    }))
+                },
+                "b2_list_buckets" => Response::json(200, json!({"buckets": [{"bucketId": "bucket-id"}]})),
+                "b2_get_upload_url" => Response::json(200, json!({"uploadUrl": format!("{}/upload", base), "authorizationToken": "upload-token"})),
+                "upload" => Response::json(200, json!({"fileId": "small-file-id"})),
+                "b2_start_large_file" => Response::json(200, json!({"fileId": "large-file-id"})),
+                "b2_get_upload_part_url" => Response::json(200, json!({"uploadUrl": format!("{}/part", base), "authorizationToken": "part-token"})),
+                "part" if fail_part.is_some_and(|n| req.header("X-Bz-Part-Number") == Some(&n.to_string())) => Response::new(503),
+                "part" => Response::json(200, json!({})),
+                "b2_finish_large_file" | "b2_cancel_large_file" => Response::json(200, json!({"fileId": "large-file-id"})),
+                _ => Response::new(404),
+            }
+        })
+    }
+
+    fn config(server: &TestServer) -> HashMap<String, HashMap<String, String>> {
+        test_server::config(HOST, &[
+            ("key_id", "key-id"),
+            ("application_key", "app-key"),
+            ("bucket", "my-bucket"),
+            ("api_url", &server.url),
+            ("part_size", "100"),
+        ])
+    }
+
+    fn sha1_of(data: &[u8]) -> String {
+        sha1_hex(&mut &data[..]).unwrap()
+    }
+
+    fn calls(server: &TestServer) -> Vec<String> {
+        server.requests().iter().map(|r| r.path.rsplit('/').next().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn small_file() {
+        let server = b2_server(None);
+        let file = test_server::upload_file("a.txt", "my file.txt", b"hello");
+        let mut s = HashMap::new();
+        let result = run(&mut UploaderClient::new().unwrap(), &file, &config(&server), &mut s).unwrap();
+
+        assert_eq!(calls(&server), ["b2_authorize_account", "b2_list_buckets", "b2_get_upload_url", "upload"]);
+        let requests = server.requests();
+        assert_eq!(requests[0].header("Authorization"), Some("Basic a2V5LWlkOmFwcC1rZXk="));
+        assert_eq!(requests[1].json(), json!({"accountId": "acct", "bucketName": "my-bucket"}));
+        assert_eq!(requests[1].header("Authorization"), Some("account-token-1"));
+        assert_eq!(requests[2].json(), json!({"bucketId": "bucket-id"}));
+
+        let upload = &requests[3];
+        assert_eq!(upload.header("Authorization"), Some("upload-token"));
+        assert_eq!(upload.header("X-Bz-File-Name"), Some("my%20file.txt"));
+        assert_eq!(upload.header("X-Bz-Content-Sha1"), Some(sha1_of(b"hello").as_str()));
+        assert_eq!(upload.header("Content-Type"), Some(AUTO_CONTENT_TYPE));
+        assert_eq!(upload.body, b"hello");
+
+        assert_eq!(result.url, format!("{}/file/my-bucket/my%20file.txt", server.url));
+        assert_eq!(result.management["file_id"], "small-file-id");
+
+        // The session's account and upload url are reused.
+        run(&mut UploaderClient::new().unwrap(), &file, &config(&server), &mut s).unwrap();
+        assert_eq!(calls(&server)[4..], ["upload"]);
+    }
+
+    #[test]
+    fn large_file() {
+        let server = b2_server(None);
+        let data: Vec<u8> = (0..250).map(|i| i as u8).collect();
+        let file = test_server::upload_file("big.bin", "big.bin", &data);
+        let result = run(&mut UploaderClient::new().unwrap(), &file, &config(&server), &mut HashMap::new()).unwrap();
+
+        assert_eq!(calls(&server), [
+            "b2_authorize_account", "b2_list_buckets", "b2_start_large_file", "b2_get_upload_part_url",
+            "part", "part", "part", "b2_finish_large_file",
+        ]);
+        let requests = server.requests();
+        assert_eq!(requests[2].json(), json!({"bucketId": "bucket-id", "fileName": "big.bin", "contentType": AUTO_CONTENT_TYPE}));
+        assert_eq!(requests[3].json(), json!({"fileId": "large-file-id"}));
+
+        let chunks: Vec<&[u8]> = data.chunks(100).collect();
+        let sha1s: Vec<String> = chunks.iter().map(|chunk| sha1_of(chunk)).collect();
+        for (i, part) in requests[4..7].iter().enumerate() {
+            assert_eq!(part.header("Authorization"), Some("part-token"));
+            assert_eq!(part.header("X-Bz-Part-Number"), Some((i + 1).to_string().as_str()));
+            assert_eq!(part.header("X-Bz-Content-Sha1"), Some(sha1s[i].as_str()));
+            assert_eq!(part.body, chunks[i]);
+        }
+        assert_eq!(requests[7].json(), json!({"fileId": "large-file-id", "partSha1Array": sha1s}));
+        assert_eq!(result.management["file_id"], "large-file-id");
+    }
+
+    #[test]
+    fn large_file_cancelled_when_a_part_fails() {
+        let server = b2_server(Some(2));
+        let file = test_server::upload_file("big.bin", "big.bin", &[7; 250]);
+        assert!(run(&mut UploaderClient::new().unwrap(), &file, &config(&server), &mut HashMap::new()).is_err());
+
+        let calls = calls(&server);
+        assert_eq!(calls[calls.len() - 3..], ["part", "part", "b2_cancel_large_file"]);
+        assert_eq!(server.requests().last().unwrap().json(), json!({"fileId": "large-file-id"}));
+    }
+
+    #[test]
+    fn expired_session_authorizes_again() {
+        let server = b2_server(None);
+        let mut s = HashMap::new();
+        for (key, value) in [
+            ("auth_token", STALE_TOKEN.to_string()),
+            ("api_url", server.url.clone()),
+            ("download_url", server.url.clone()),
+            ("bucket", "my-bucket".to_string()),
+            ("bucket_id", "bucket-id".to_string()),
+            ("part_size", "100".to_string()),
+            ("min_part_size", "5".to_string()),
+        ] {
+            utils::set_session_val(&mut s, HOST, key, &value);
+        }
+
+        let file = test_server::upload_file("a.txt", "a.txt", b"hello");
+        let result = run(&mut UploaderClient::new().unwrap(), &file, &config(&server), &mut s).unwrap();
+        assert_eq!(result.management["file_id"], "small-file-id");
+
+        assert_eq!(calls(&server), ["b2_get_upload_url", "b2_authorize_account", "b2_list_buckets", "b2_get_upload_url", "upload"]);
+        let requests = server.requests();
+        assert_eq!(requests[0].header("Authorization"), Some(STALE_TOKEN));
+        assert_eq!(requests[3].header("Authorization"), Some("account-token-1"));
+        assert_eq!(utils::get_session_val(&s, HOST, "auth_token"), "account-token-1");
+    }
+
+    #[test]
+    fn invalid_key() {
+        let server = TestServer::new(|_| Response::json(401, json!({"status": 401, "code": "unauthorized"})));
+        let file = test_server::upload_file("a.txt", "a.txt", b"hello");
+        let err = run(&mut UploaderClient::new().unwrap(), &file, &config(&server), &mut HashMap::new()).err().unwrap();
+        assert!(err.is::<InvalidAPIKeyError>());
+        // Without a session there's nothing to refresh, so it's only tried once.
+        assert_eq!(server.requests().len(), 1);
+    }
+}
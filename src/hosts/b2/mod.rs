@@ -0,0 +1,5 @@
+#[allow(clippy::module_inception)]
+pub mod b2;
+mod structs;
+
+pub use b2::{delete, run};
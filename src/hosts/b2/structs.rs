@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Allowed {
+    pub bucket_id: Option<String>,
+    pub bucket_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorizeResp {
+    pub account_id: String,
+    pub authorization_token: String,
+    pub api_url: String,
+    pub download_url: String,
+    pub recommended_part_size: u64,
+    pub absolute_minimum_part_size: u64,
+    pub allowed: Allowed,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListBucketsPost {
+    pub account_id: String,
+    pub bucket_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bucket {
+    pub bucket_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct ListBucketsResp {
+    pub buckets: Vec<Bucket>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUploadUrlPost {
+    pub bucket_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileIdPost {
+    pub file_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadUrlResp {
+    pub upload_url: String,
+    pub authorization_token: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartLargeFilePost {
+    pub bucket_id: String,
+    pub file_name: String,
+    pub content_type: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileResp {
+    pub file_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinishLargeFilePost {
+    pub file_id: String,
+    pub part_sha1_array: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteFileVersionPost {
+    pub file_name: String,
+    pub file_id: String,
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::error::Error;
+use crate::client::UploaderClient;
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const API_URL: &str = "https://catbox.moe/user/api.php";
+const FILES_BASE: &str = "https://files.catbox.moe/";
+const HOST: &str = "catbox";
+
+// The api answers with a bare url on success and an error message otherwise.
+fn check_resp_url(text: String) -> Result<String, Box<dyn Error>> {
+    let text = text.trim().to_string();
+    if !text.starts_with("https://") {
+        return Err(format!("api reported failure: {}", text).into())
+    }
+    Ok(text)
+}
+
+fn create_album(c: &mut UploaderClient, catbox_cfg: &HashMap<String, String>, filenames: &str) -> Result<String, Box<dyn Error>> {
+    let userhash = utils::get_config_val(catbox_cfg, "userhash", false)?;
+    let title = utils::get_config_val(catbox_cfg, "album_title", false)?;
+    let desc = utils::get_config_val(catbox_cfg, "album_desc", false)?;
+
+    let fields: Vec<(String, String)> = vec![
+        ("reqtype".to_string(), "createalbum".to_string()),
+        ("userhash".to_string(), userhash),
+        ("title".to_string(), title),
+        ("desc".to_string(), desc),
+        ("files".to_string(), filenames.to_string()),
+    ];
+
+    let resp = c.do_form_post(API_URL, None, &fields)?;
+    check_resp_url(resp.text()?)
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    utils::check_file_size_limit(file.size, Size::MB200)?;
+
+    let catbox_cfg = utils::get_section_or_empty(cfg, HOST);
+    let userhash = utils::get_config_val(&catbox_cfg, "userhash", false)?;
+
+    let mut fields: Vec<(String, String)> = vec![
+        ("reqtype".to_string(), "fileupload".to_string()),
+    ];
+    if !userhash.is_empty() {
+        fields.push(("userhash".to_string(), userhash));
+    }
+
+    let resp = c.multipart_upload(API_URL, "fileToUpload".to_string(), file, None, Some(fields), None)?;
+    let file_url = check_resp_url(resp.text()?)?;
+    let filename = file_url.trim_start_matches(FILES_BASE).to_string();
+
+    let mut filenames = utils::get_session_val(s, HOST, "filenames");
+    if !filenames.is_empty() {
+        filenames.push(' ');
+    }
+    filenames.push_str(&filename);
+    utils::set_session_val(s, HOST, "filenames", &filenames);
+
+    let mut result = UploadResult::new(file_url);
+    result.management.insert("filename".to_string(), filename);
+    Ok(result)
+}
+
+// Bundles every file uploaded in this batch into an album.
+pub fn finish(c: &mut UploaderClient, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<Option<String>, Box<dyn Error>> {
+    let catbox_cfg = utils::get_section_or_empty(cfg, HOST);
+    let album_enabled = utils::get_config_val(&catbox_cfg, "create_album", false)?;
+    let filenames = utils::get_session_val(s, HOST, "filenames");
+    // Reset first, so neither a disabled nor a failed album carries files into the next batch.
+    utils::set_session_val(s, HOST, "filenames", "");
+
+    if album_enabled != "y" || filenames.is_empty() {
+        return Ok(None)
+    }
+
+    let album_url = create_album(c, &catbox_cfg, &filenames)?;
+    Ok(Some(album_url))
+}
+
+// Only works for files uploaded with the configured userhash.
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let filename = result.management.get("filename").ok_or("upload has no catbox filename")?;
+
+    let catbox_cfg = utils::get_section_or_empty(cfg, HOST);
+    let userhash = utils::get_config_val(&catbox_cfg, "userhash", true)?;
+
+    let fields: Vec<(String, String)> = vec![
+        ("reqtype".to_string(), "deletefiles".to_string()),
+        ("userhash".to_string(), userhash),
+        ("files".to_string(), filename.to_string()),
+    ];
+
+    let resp = c.do_form_post(API_URL, None, &fields)?;
+    let text = resp.text()?;
+    if !text.to_lowercase().contains("success") {
+        return Err(format!("api reported failure: {}", text.trim()).into())
+    }
+    Ok(())
+}
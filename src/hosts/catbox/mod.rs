@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod catbox;
+
+pub use catbox::{delete, finish, run};
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use mime_guess::MimeGuess;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use regex::{Captures, Regex};
+use reqwest::blocking::Response as ReqwestResp;
+use reqwest::{Method, StatusCode};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, LOCATION};
+use serde_json::Value;
+use url::Url;
+use crate::client::UploaderClient;
+use crate::hosts::errors::{InvalidAPIKeyError, SectionKeyMissingError, UnexpectedResponseError};
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const SECTION_PREFIX: &str = "custom";
+const DEFAULT_FILE_FIELD: &str = "file";
+// Unreserved characters stay as they are, so <filename> works as a path segment or query value.
+const FILENAME_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+// Where the file's url is read from in the response.
+enum ResultSource {
+    Body,
+    Json(String),
+    Regex(Regex),
+    Location,
+}
+
+impl ResultSource {
+    fn from_config(host_cfg: &HashMap<String, String>) -> Result<ResultSource, Box<dyn Error>> {
+        match utils::get_config_val(host_cfg, "result", false)?.as_str() {
+            "" | "body" => Ok(ResultSource::Body),
+            "json" => Ok(ResultSource::Json(utils::get_config_val(host_cfg, "json_pointer", true)?)),
+            "regex" => Ok(ResultSource::Regex(Regex::new(&utils::get_config_val(host_cfg, "regex", true)?)?)),
+            "location" => Ok(ResultSource::Location),
+            other => Err(format!("unknown result source: {}, expected body, json, regex or location", other).into()),
+        }
+    }
+
+    fn extract(&self, resp: ReqwestResp, req_url: &Url) -> Result<String, Box<dyn Error>> {
+        match self {
+            ResultSource::Body => {
+                let text = resp.text()?;
+                let url = text.trim();
+                if url.is_empty() {
+                    return Err(UnexpectedResponseError.into())
+                }
+                Ok(url.to_string())
+            },
+            ResultSource::Json(pointer) => {
+                let json_obj: Value = resp.json()?;
+                match json_obj.pointer(pointer) {
+                    Some(Value::String(url)) => Ok(url.to_string()),
+                    Some(Value::Null) | None => Err(UnexpectedResponseError.into()),
+                    Some(value) => Ok(value.to_string()),
+                }
+            },
+            ResultSource::Regex(re) => {
+                let text = resp.text()?;
+                let caps = re.captures(&text).ok_or(UnexpectedResponseError)?;
+                // The first group if there is one, otherwise the whole match. A group that didn't match isn't the url.
+                let group = if re.captures_len() > 1 { 1 } else { 0 };
+                let m = caps.get(group).ok_or(UnexpectedResponseError)?;
+                Ok(m.as_str().to_string())
+            },
+            ResultSource::Location => {
+                if let Some(location) = resp.headers().get(LOCATION) {
+                    return Ok(resp.url().join(location.to_str()?)?.to_string())
+                }
+                // Redirects are followed, so the final url is the file's.
+                if resp.url() != req_url {
+                    return Ok(resp.url().to_string())
+                }
+                Err(UnexpectedResponseError.into())
+            },
+        }
+    }
+}
+
+// Secrets come from the environment as <env:NAME> so they don't have to be stored in the config.
+fn render(template: &str, vars: &[(&str, &str)]) -> Result<String, Box<dyn Error>> {
+    let re = Regex::new(r"<env:([A-Za-z_][A-Za-z0-9_]*)>")?;
+    let mut missing: Option<String> = None;
+    let rendered = re.replace_all(template, |caps: &Captures| {
+        env::var(&caps[1]).unwrap_or_else(|_| {
+            missing = Some(caps[1].to_string());
+            String::new()
+        })
+    });
+    if let Some(name) = missing {
+        return Err(format!("environment variable {} isn't set", name).into())
+    }
+
+    let mut rendered = utils::render_date_vars(&rendered);
+    for (name, value) in vars {
+        rendered = rendered.replace(&format!("<{}>", name), value);
+    }
+    Ok(rendered)
+}
+
+// Sorted so requests are built the same way every run.
+fn get_sorted_section(cfg: &HashMap<String, HashMap<String, String>>, section: &str) -> Vec<(String, String)> {
+    let mut entries: Vec<(String, String)> = utils::get_section_or_empty(cfg, section).into_iter().collect();
+    entries.sort();
+    entries
+}
+
+fn make_headers(cfg: &HashMap<String, HashMap<String, String>>, section: &str, vars: &[(&str, &str)]) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in get_sorted_section(cfg, &format!("{}.headers", section)) {
+        headers.insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(&render(&value, vars)?)?);
+    }
+    Ok(headers)
+}
+
+fn make_fields(cfg: &HashMap<String, HashMap<String, String>>, section: &str, vars: &[(&str, &str)]) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    get_sorted_section(cfg, &format!("{}.fields", section))
+        .into_iter()
+        .map(|(name, value)| Ok((name, render(&value, vars)?)))
+        .collect()
+}
+
+// Checked before uploading anything, so a typo in --hosts doesn't stop a run halfway through.
+pub fn check_defined(name: &str, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let section = format!("{}.{}", SECTION_PREFIX, name);
+    if !cfg.contains_key(&section) {
+        return Err(format!("unknown host: {}, it isn't built in and the config has no [{}] section", name, section).into())
+    }
+    Ok(())
+}
+
+pub fn run(c: &mut UploaderClient, name: &str, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let section = format!("{}.{}", SECTION_PREFIX, name);
+    let host_cfg = cfg.get(&section).ok_or_else(|| SectionKeyMissingError::new(&section))?;
+
+    let max_size = utils::get_config_val(host_cfg, "max_size", false)?;
+    if !max_size.is_empty() {
+        utils::check_file_size_limit(file.size, Size::custom_from_str(&max_size)?)?;
+    }
+
+    let method = match utils::get_config_val(host_cfg, "method", false)?.as_str() {
+        "" => Method::POST,
+        method => Method::from_bytes(method.to_uppercase().as_bytes())?,
+    };
+    let result_source = ResultSource::from_config(host_cfg)?;
+
+    let size = file.size.to_string();
+    let mime = MimeGuess::from_path(&file.name).first_or_octet_stream().to_string();
+    let vars = [
+        ("filename", file.name.as_str()),
+        ("mime", &mime),
+        ("size", &size),
+    ];
+
+    let encoded_name = utf8_percent_encode(&file.name, FILENAME_ENCODE_SET).to_string();
+    let mut url_vars = vars;
+    url_vars[0] = ("filename", &encoded_name);
+    let mut url = Url::parse(&render(&utils::get_config_val(host_cfg, "url", true)?, &url_vars)?)?;
+
+    let headers = make_headers(cfg, &section, &vars)?;
+    let fields = make_fields(cfg, &section, &vars)?;
+
+    let resp = match utils::get_config_val(host_cfg, "body", false)?.as_str() {
+        "" | "multipart" => {
+            let mut file_field = utils::get_config_val(host_cfg, "file_field", false)?;
+            if file_field.is_empty() {
+                file_field = DEFAULT_FILE_FIELD.to_string();
+            }
+            c.multipart_request(method, url.as_str(), file_field, file, Some(headers), Some(fields), None)
+        },
+        "raw" => {
+            // Raw bodies have nowhere else to put form fields.
+            if !fields.is_empty() {
+                url.query_pairs_mut().extend_pairs(&fields);
+            }
+            c.body_upload(method, url.as_str(), &file.path, Some(headers))
+        },
+        other => return Err(format!("unknown body type: {}, expected multipart or raw", other).into()),
+    };
+
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(err) => {
+            if let Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) = utils::get_error_status(err.as_ref()) {
+                return Err(InvalidAPIKeyError.into())
+            }
+            return Err(err)
+        },
+    };
+
+    Ok(UploadResult::new(result_source.extract(resp, &url)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_server::{self, Request, Response, TestServer};
+    use super::*;
+
+    fn host_config(values: &[(&str, &str)], fields: &[(&str, &str)], headers: &[(&str, &str)]) -> HashMap<String, HashMap<String, String>> {
+        let mut cfg = test_server::config("custom.myhost", values);
+        cfg.extend(test_server::config("custom.myhost.fields", fields));
+        cfg.extend(test_server::config("custom.myhost.headers", headers));
+        cfg
+    }
+
+    fn upload(server: &TestServer, values: &[(&str, &str)]) -> Result<UploadResult, Box<dyn Error>> {
+        let url = format!("{}/upload", server.url);
+        let mut values = values.to_vec();
+        values.push(("url", &url));
+        let file = test_server::upload_file("a.txt", "a.txt", b"hello");
+        run(&mut UploaderClient::new().unwrap(), "myhost", &file, &host_config(&values, &[], &[]))
+    }
+
+    #[test]
+    fn json_pointer_with_env_header() {
+        env::set_var("UPLOADER_RS_TEST_CUSTOM_TOKEN", "secret");
+        let server = TestServer::new(|_| Response::json(200, serde_json::json!({"data": {"url": "https://files.example/abc"}})));
+        let cfg = host_config(
+            &[
+                ("url", &format!("{}/upload?name=<filename>", server.url)),
+                ("file_field", "upload"),
+                ("result", "json"),
+                ("json_pointer", "/data/url"),
+            ],
+            &[("expires", "7d"), ("title", "<filename> (<size> bytes)")],
+            &[("Authorization", "Bearer <env:UPLOADER_RS_TEST_CUSTOM_TOKEN>")],
+        );
+        let file = test_server::upload_file("a.txt", "my file.txt", b"hello");
+        let result = run(&mut UploaderClient::new().unwrap(), "myhost", &file, &cfg).unwrap();
+        assert_eq!(result.url, "https://files.example/abc");
+
+        let req = &server.requests()[0];
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/upload?name=my%20file.txt");
+        assert_eq!(req.header("Authorization"), Some("Bearer secret"));
+        assert!(req.header("Content-Type").unwrap().starts_with("multipart/form-data"));
+        let body = req.text();
+        assert!(body.contains("name=\"upload\"; filename=\"my file.txt\""));
+        assert!(body.contains("name=\"expires\"\r\n\r\n7d\r\n"));
+        assert!(body.contains("name=\"title\"\r\n\r\nmy file.txt (5 bytes)\r\n"));
+    }
+
+    #[test]
+    fn raw_body() {
+        let server = TestServer::new(|_| Response::text(200, "  https://files.example/abc\n"));
+        let cfg = host_config(
+            &[("url", &format!("{}/up/<filename>", server.url)), ("method", "put"), ("body", "raw")],
+            &[("expires", "7d")],
+            &[("X-Size", "<size>")],
+        );
+        let file = test_server::upload_file("a.txt", "my file.txt", b"hello");
+        let result = run(&mut UploaderClient::new().unwrap(), "myhost", &file, &cfg).unwrap();
+        assert_eq!(result.url, "https://files.example/abc");
+
+        let req = &server.requests()[0];
+        assert_eq!(req.method, "PUT");
+        assert_eq!(req.path, "/up/my%20file.txt?expires=7d");
+        assert_eq!(req.header("X-Size"), Some("5"));
+        assert_eq!(req.body, b"hello");
+    }
+
+    #[test]
+    fn empty_body() {
+        let server = TestServer::new(|_| Response::text(200, " \n"));
+        let err = upload(&server, &[]).err().unwrap();
+        assert!(err.is::<UnexpectedResponseError>());
+    }
+
+    #[test]
+    fn regex() {
+        let server = TestServer::new(|_| Response::text(200, r#"<a href="https://files.example/abc">done</a>"#));
+        let result = upload(&server, &[("result", "regex"), ("regex", r#"href="([^"]+)""#)]).unwrap();
+        assert_eq!(result.url, "https://files.example/abc");
+
+        // Without a group it's the whole match.
+        let result = upload(&server, &[("result", "regex"), ("regex", r"https://\S+/abc")]).unwrap();
+        assert_eq!(result.url, "https://files.example/abc");
+    }
+
+    #[test]
+    fn regex_missing_group() {
+        let server = TestServer::new(|_| Response::text(200, "done"));
+        let err = upload(&server, &[("result", "regex"), ("regex", r"url: (\S+)|done")]).err().unwrap();
+        assert!(err.is::<UnexpectedResponseError>());
+
+        let err = upload(&server, &[("result", "regex"), ("regex", r"url: (\S+)")]).err().unwrap();
+        assert!(err.is::<UnexpectedResponseError>());
+    }
+
+    #[test]
+    fn location() {
+        let server = TestServer::new(|_| Response::new(201).header("Location", "/files/abc"));
+        let result = upload(&server, &[("result", "location")]).unwrap();
+        assert_eq!(result.url, format!("{}/files/abc", server.url));
+    }
+
+    #[test]
+    fn location_after_redirect() {
+        let server = TestServer::new(|req: &Request| match req.path.as_str() {
+            "/upload" => Response::new(303).header("Location", "/files/abc"),
+            _ => Response::text(200, "file page"),
+        });
+        let result = upload(&server, &[("result", "location")]).unwrap();
+        assert_eq!(result.url, format!("{}/files/abc", server.url));
+        assert_eq!(server.paths(), ["POST /upload", "GET /files/abc"]);
+    }
+
+    #[test]
+    fn missing_env_var() {
+        let server = TestServer::new(|_| Response::text(200, "https://files.example/abc"));
+        let cfg = host_config(
+            &[("url", &format!("{}/upload", server.url))],
+            &[],
+            &[("Authorization", "Bearer <env:UPLOADER_RS_TEST_CUSTOM_UNSET>")],
+        );
+        let file = test_server::upload_file("a.txt", "a.txt", b"hello");
+        let err = run(&mut UploaderClient::new().unwrap(), "myhost", &file, &cfg).err().unwrap();
+        assert_eq!(err.to_string(), "environment variable UPLOADER_RS_TEST_CUSTOM_UNSET isn't set");
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn rejected_key() {
+        let server = TestServer::new(|_| Response::text(403, "forbidden"));
+        let err = upload(&server, &[]).err().unwrap();
+        assert!(err.is::<InvalidAPIKeyError>());
+    }
+}
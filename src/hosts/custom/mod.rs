@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod custom;
+
+pub use custom::{check_defined, run};
@@ -1,14 +1,98 @@
+use std::ffi::OsStr;
+use std::fmt;
+use clap::builder::{PossibleValue, TypedValueParser};
+use clap::error::ErrorKind;
 use clap::ValueEnum;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ValueEnum)]
 pub enum Host {
+    Azblob,
+    B2,
+    Catbox,
     Fileio,
+    Ftp,
+    Gcs,
     Gofile,
+    Ipfs,
+    Litterbox,
+    Nullpointer,
     Pixeldrain,
+    S3,
+    Sftp,
+    Transfersh,
+    Webdav,
 }
 
 impl Host {
-    pub fn to_string(&self) -> String {
-        format!("{:?}", self).to_lowercase()
+    // The management value that identifies a single upload, which delete matches on besides the URL.
+    pub fn id_key(&self) -> Option<&'static str> {
+        match self {
+            Host::B2 | Host::Gofile | Host::Pixeldrain => Some("file_id"),
+            Host::Fileio | Host::S3 => Some("key"),
+            Host::Ftp | Host::Sftp => Some("remote_path"),
+            Host::Ipfs => Some("cid"),
+            Host::Webdav => Some("dav_url"),
+            // Containers, buckets, bare object names, catbox filenames, tokens and delete urls can be shared by
+            // many uploads, so these only match through the URL or --key.
+            Host::Azblob | Host::Catbox | Host::Gcs | Host::Litterbox | Host::Nullpointer | Host::Transfersh => None,
+        }
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+// A built-in host, or one defined under [custom.<name>] in the config.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HostArg {
+    Builtin(Host),
+    Custom(String),
+}
+
+impl HostArg {
+    // Built-in names win over custom hosts with the same name.
+    pub fn parse(s: &str) -> HostArg {
+        match Host::from_str(s, true) {
+            Ok(host) => HostArg::Builtin(host),
+            Err(_) => HostArg::Custom(s.to_string()),
+        }
+    }
+
+    // Names with dots would clash with the nested [custom.<name>.headers] style sections.
+    pub fn from_name(s: &str) -> Result<HostArg, String> {
+        if s.is_empty() || s.contains('.') {
+            return Err(format!("invalid host name: {}", s))
+        }
+        Ok(HostArg::parse(s))
+    }
+}
+
+impl fmt::Display for HostArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostArg::Builtin(host) => write!(f, "{}", host),
+            HostArg::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+// Accepts any name so custom hosts can be used, but lists the built-in ones in --help.
+#[derive(Clone)]
+pub struct HostArgParser;
+
+impl TypedValueParser for HostArgParser {
+    type Value = HostArg;
+
+    fn parse_ref(&self, cmd: &clap::Command, _: Option<&clap::Arg>, value: &OsStr) -> Result<HostArg, clap::Error> {
+        value.to_str()
+            .and_then(|s| HostArg::from_name(s).ok())
+            .ok_or_else(|| clap::Error::new(ErrorKind::InvalidValue).with_cmd(cmd))
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(Host::value_variants().iter().filter_map(|host| host.to_possible_value())))
+    }
+}
@@ -75,4 +75,26 @@ impl fmt::Debug for SectionValueEmptyError {
         write!(f, "required value for key \"{}\" is empty in config file", self.message)
     }
 }
-impl Error for SectionValueEmptyError {}
\ No newline at end of file
+impl Error for SectionValueEmptyError {}
+
+#[derive(Debug)]
+pub struct InvalidAPIKeyError;
+
+impl std::fmt::Display for InvalidAPIKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the api rejected the configured api key")
+    }
+}
+
+impl Error for InvalidAPIKeyError {}
+
+#[derive(Debug)]
+pub struct UnexpectedResponseError;
+
+impl std::fmt::Display for UnexpectedResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the api response layout changed")
+    }
+}
+
+impl Error for UnexpectedResponseError {}
@@ -1,20 +1,81 @@
 use std::collections::HashMap;
 use std::error::Error;
-use std::path::PathBuf;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
 use crate::client::UploaderClient;
-use crate::hosts::fileio::structs::UploadResp;
+use crate::hosts::errors::BadAPIResponseError;
+use crate::hosts::fileio::structs::{DeleteResp, UploadResp};
 use crate::hosts::sizes::Size;
-use crate::hosts::utils::check_file_size_limit;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
 
 const UPLOAD_URL: &str = "https://file.io";
-pub fn run(c: &mut UploaderClient, file_path: &PathBuf, file_size: usize, _cfg: &HashMap<String, HashMap<String, String>>, _s: &mut HashMap<String, HashMap<String, String>>) -> Result<String, Box<dyn Error>>{
-    check_file_size_limit(file_size, Size::GB2)?;
+const HOST: &str = "fileio";
 
-    let resp = c.multipart_upload(UPLOAD_URL, "file".to_string(), file_path, None, None, None)?;
+fn make_fields(fileio_cfg: &HashMap<String, String>) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    let expires = utils::get_config_val(fileio_cfg, "expires", false)?;
+    if !expires.is_empty() {
+        fields.push(("expires".to_string(), expires));
+    }
+
+    let max_downloads = utils::get_config_val(fileio_cfg, "max_downloads", false)?;
+    if !max_downloads.is_empty() {
+        max_downloads.parse::<u32>()
+            .map_err(|_| format!("invalid max_downloads value: {}", max_downloads))?;
+        fields.push(("maxDownloads".to_string(), max_downloads));
+    }
+
+    let auto_delete = utils::get_config_val(fileio_cfg, "auto_delete", false)?;
+    if !auto_delete.is_empty() {
+        let value = if auto_delete == "y" { "true" } else { "false" };
+        fields.push(("autoDelete".to_string(), value.to_string()));
+    }
+
+    Ok(fields)
+}
+
+fn make_headers(fileio_cfg: &HashMap<String, String>) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    let api_key = utils::get_config_val(fileio_cfg, "api_key", false)?;
+    if !api_key.is_empty() {
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", api_key))?);
+    }
+    Ok(headers)
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, _s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>>{
+    utils::check_file_size_limit(file.size, Size::GB2)?;
+
+    let fileio_cfg = utils::get_section_or_empty(cfg, HOST);
+    let fields = make_fields(&fileio_cfg)?;
+
+    let headers = make_headers(&fileio_cfg)?;
+    let resp = c.multipart_upload(UPLOAD_URL, "file".to_string(), file, Some(headers), Some(fields), None)?;
     let json_obj: UploadResp = resp.json()?;
     if !json_obj.success {
         return Err("api reported failure".into())
     }
 
-    Ok(json_obj.link)
+    let mut result = UploadResult::new(json_obj.link);
+    result.expires = json_obj.expires;
+    result.management.insert("key".to_string(), json_obj.key);
+    Ok(result)
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let key = result.management.get("key").ok_or("upload has no file.io key")?;
+
+    let fileio_cfg = utils::get_section_or_empty(cfg, HOST);
+    let headers = make_headers(&fileio_cfg)?;
+
+    let url = format!("{}/{}", UPLOAD_URL, key);
+    let resp = c.do_delete::<()>(&url, Some(headers), None)?;
+    let json_obj: DeleteResp = resp.json()?;
+    if !json_obj.success {
+        return Err(BadAPIResponseError.into())
+    }
+
+    Ok(())
 }
@@ -1,4 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod fileio;
 mod structs;
 
-pub use fileio::run;
\ No newline at end of file
+pub use fileio::{delete, run};
\ No newline at end of file
@@ -2,6 +2,13 @@ use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct UploadResp {
+    pub key: String,
     pub link: String,
     pub success: bool,
-}
\ No newline at end of file
+    pub expires: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteResp {
+    pub success: bool,
+}
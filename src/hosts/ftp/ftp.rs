@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use suppaftp::{FtpError, NativeTlsConnector, NativeTlsFtpStream, Status};
+use suppaftp::native_tls::TlsConnector;
+use suppaftp::types::FileType;
+use crate::client::structs::ProgressReader;
+use crate::client::UploaderClient;
+use crate::hosts::errors::InvalidAPIKeyError;
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const HOST: &str = "ftp";
+const DEFAULT_PORT: u16 = 21;
+const DEFAULT_IMPLICIT_PORT: u16 = 990;
+
+fn make_tls_connector(ftp_cfg: &HashMap<String, String>) -> Result<NativeTlsConnector, Box<dyn Error>> {
+    let insecure = utils::get_config_val(ftp_cfg, "tls_insecure", false)? == "y";
+    let connector = TlsConnector::builder()
+        .danger_accept_invalid_certs(insecure)
+        .build()?;
+    Ok(NativeTlsConnector::from(connector))
+}
+
+// Plain FTP also goes through the TLS stream type, it only upgrades when asked to.
+fn connect(ftp_cfg: &HashMap<String, String>) -> Result<NativeTlsFtpStream, Box<dyn Error>> {
+    let host = utils::get_config_val(ftp_cfg, "host", true)?;
+    let tls = utils::get_config_val(ftp_cfg, "tls", false)?;
+    let port = match utils::get_config_val(ftp_cfg, "port", false)?.as_str() {
+        "" if tls == "implicit" => DEFAULT_IMPLICIT_PORT,
+        "" => DEFAULT_PORT,
+        port => port.parse::<u16>()?,
+    };
+    let addr = (host.as_str(), port);
+
+    let mut ftp = match tls.as_str() {
+        "" => NativeTlsFtpStream::connect(addr)?,
+        "explicit" => NativeTlsFtpStream::connect(addr)?
+            .into_secure(make_tls_connector(ftp_cfg)?, &host)?,
+        "implicit" => NativeTlsFtpStream::connect_secure_implicit(addr, make_tls_connector(ftp_cfg)?, &host)?,
+        _ => return Err("tls must be empty, explicit or implicit".into()),
+    };
+
+    let mut username = utils::get_config_val(ftp_cfg, "username", false)?;
+    if username.is_empty() {
+        username = "anonymous".to_string();
+    }
+    let password = utils::get_config_val(ftp_cfg, "password", false)?;
+
+    match ftp.login(&username, &password) {
+        Ok(()) => {},
+        Err(FtpError::UnexpectedResponse(resp)) if resp.status == Status::NotLoggedIn => {
+            return Err(InvalidAPIKeyError.into())
+        },
+        Err(err) => return Err(err.into()),
+    }
+
+    ftp.transfer_type(FileType::Binary)?;
+    Ok(ftp)
+}
+
+fn make_dirs(ftp: &mut NativeTlsFtpStream, dirs: &[String]) -> Result<(), Box<dyn Error>> {
+    for i in 1..=dirs.len() {
+        let path = utils::join_remote_path(true, &dirs[..i]);
+        if ftp.cwd(&path).is_err() {
+            ftp.mkdir(&path)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run(_: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, _: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let ftp_cfg = utils::get_section_or_empty(cfg, HOST);
+
+    let max_size = utils::get_config_val(&ftp_cfg, "max_size", false)?;
+    if !max_size.is_empty() {
+        utils::check_file_size_limit(file.size, Size::custom_from_str(&max_size)?)?;
+    }
+
+    let public_url = utils::get_config_val(&ftp_cfg, "public_url", true)?;
+    let mut remote_dir = utils::get_config_val(&ftp_cfg, "remote_dir", false)?;
+
+    let mut ftp = connect(&ftp_cfg)?;
+
+    // Relative dirs are resolved against the login dir up front, since checking dirs moves the cwd.
+    if !remote_dir.starts_with('/') {
+        remote_dir = format!("{}/{}", ftp.pwd()?, remote_dir);
+    }
+    let mut dirs = utils::get_remote_dirs(&remote_dir, &file.rel_dir);
+    make_dirs(&mut ftp, &dirs)?;
+
+    dirs.push(file.name.clone());
+    let remote_path = utils::join_remote_path(true, &dirs);
+    let mut reader = ProgressReader::new(File::open(&file.path)?, file.size as u64)?;
+    ftp.put_file(&remote_path, &mut reader)?;
+    // Some servers drop the connection right after the transfer, the file is already stored by then.
+    let _ = ftp.quit();
+
+    let mut segments = utils::get_dir_segments(&file.rel_dir);
+    segments.push(file.name.clone());
+    let mut result = UploadResult::new(utils::join_url_segments(&public_url, &segments)?);
+    result.management.insert("remote_path".to_string(), remote_path);
+    Ok(result)
+}
+
+pub fn delete(_: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let remote_path = result.management.get("remote_path").ok_or("upload has no remote path")?;
+
+    let ftp_cfg = utils::get_section_or_empty(cfg, HOST);
+    let mut ftp = connect(&ftp_cfg)?;
+    ftp.rm(remote_path)?;
+    let _ = ftp.quit();
+    Ok(())
+}
@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod ftp;
+
+pub use ftp::{delete, run};
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use indicatif::ProgressBar;
+use mime_guess::MimeGuess;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::blocking::Body;
+use reqwest::header::{AUTHORIZATION, CONTENT_RANGE, HeaderMap, HeaderValue, LOCATION, RANGE};
+use reqwest::{Method, StatusCode};
+use crate::client::structs::{new_progress_bar, ProgressReader};
+use crate::client::UploaderClient;
+use crate::hosts::errors::{InvalidAPIKeyError, UnexpectedResponseError};
+use crate::hosts::gcs::oauth;
+use crate::hosts::gcs::structs::ObjectMetadata;
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const HOST: &str = "gcs";
+const DEFAULT_ENDPOINT: &str = "https://storage.googleapis.com";
+const MAX_OBJECT_SIZE: usize = 5_000_000_000_000;
+// Chunks other than the last have to be a multiple of 256 KiB.
+const CHUNK_ALIGN: u64 = 256 * 1024;
+const DEFAULT_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+const PATH_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'/');
+// For object names inside JSON API paths, where slashes are part of the name.
+const SEGMENT_ENCODE_SET: &AsciiSet = &PATH_ENCODE_SET.add(b'/');
+
+struct GcsConfig {
+    endpoint: String,
+    bucket: String,
+    name_prefix: String,
+    chunk_size: u64,
+    credentials: String,
+}
+
+fn load_config(cfg: &HashMap<String, HashMap<String, String>>) -> Result<GcsConfig, Box<dyn Error>> {
+    let gcs_cfg = utils::get_section_or_empty(cfg, HOST);
+
+    let mut endpoint = utils::get_config_val(&gcs_cfg, "endpoint", false)?;
+    if endpoint.is_empty() {
+        endpoint = DEFAULT_ENDPOINT.to_string();
+    }
+
+    let chunk_size = match utils::get_config_val(&gcs_cfg, "chunk_size", false)?.as_str() {
+        "" => DEFAULT_CHUNK_SIZE,
+        value => value.parse::<u64>()
+            .map_err(|_| format!("invalid chunk_size value: {}", value))?,
+    };
+    if chunk_size == 0 || chunk_size % CHUNK_ALIGN != 0 {
+        return Err(format!("chunk_size must be a multiple of {} bytes", CHUNK_ALIGN).into())
+    }
+
+    let gcs_config = GcsConfig {
+        endpoint: endpoint.trim_end_matches('/').to_string(),
+        bucket: utils::get_config_val(&gcs_cfg, "bucket", true)?,
+        name_prefix: utils::get_config_val(&gcs_cfg, "name_prefix", false)?,
+        chunk_size,
+        credentials: utils::get_config_val(&gcs_cfg, "credentials", false)?,
+    };
+    Ok(gcs_config)
+}
+
+// No credentials means no auth, for emulators like fake-gcs-server.
+fn make_headers(c: &mut UploaderClient, gcs_config: &GcsConfig, s: &mut HashMap<String, HashMap<String, String>>) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    if gcs_config.credentials.is_empty() {
+        return Ok(headers)
+    }
+
+    // Tokens last an hour, watch mode keeps the session for much longer than that.
+    let mut token = utils::get_session_val(s, HOST, "access_token");
+    let refresh_at = utils::get_session_val(s, HOST, "refresh_at").parse::<i64>().unwrap_or(0);
+    if token.is_empty() || Utc::now().timestamp() >= refresh_at {
+        let access_token = oauth::get_access_token(c, Path::new(&gcs_config.credentials))?;
+        utils::set_session_val(s, HOST, "access_token", &access_token.token);
+        utils::set_session_val(s, HOST, "refresh_at", &access_token.refresh_at.to_string());
+        token = access_token.token;
+    }
+
+    let mut value = HeaderValue::from_str(&format!("Bearer {}", token))?;
+    value.set_sensitive(true);
+    headers.insert(AUTHORIZATION, value);
+    Ok(headers)
+}
+
+fn map_auth_error(err: Box<dyn Error>) -> Box<dyn Error> {
+    match utils::get_error_status(err.as_ref()) {
+        Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => InvalidAPIKeyError.into(),
+        _ => err,
+    }
+}
+
+fn start_session(c: &mut UploaderClient, gcs_config: &GcsConfig, name: &str, file_size: u64, headers: &HeaderMap) -> Result<String, Box<dyn Error>> {
+    let url = format!("{}/upload/storage/v1/b/{}/o?uploadType=resumable", gcs_config.endpoint, gcs_config.bucket);
+    let content_type = MimeGuess::from_path(name).first_or_octet_stream().to_string();
+
+    let mut headers = headers.clone();
+    headers.insert("X-Upload-Content-Type", HeaderValue::from_str(&content_type)?);
+    headers.insert("X-Upload-Content-Length", HeaderValue::from(file_size));
+
+    let metadata = ObjectMetadata {
+        name: name.to_string(),
+        content_type,
+    };
+    let resp = c.do_post(&url, Some(headers), Some(&metadata)).map_err(map_auth_error)?;
+
+    let session_url = resp.headers().get(LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .ok_or(UnexpectedResponseError)?;
+    Ok(session_url.to_string())
+}
+
+// A 308 says how much the server has so far, which can be less than was sent.
+fn get_persisted_end(headers: &HeaderMap) -> Result<u64, Box<dyn Error>> {
+    let Some(range) = headers.get(RANGE) else {
+        return Ok(0)
+    };
+    let last = range.to_str()?
+        .rsplit('-')
+        .next()
+        .ok_or(UnexpectedResponseError)?;
+    Ok(last.parse::<u64>()? + 1)
+}
+
+fn upload_chunks(c: &mut UploaderClient, gcs_config: &GcsConfig, session_url: &str, file_path: &PathBuf, file_size: u64, pb: &ProgressBar) -> Result<(), Box<dyn Error>> {
+    // Empty files are finished by telling the session the total size.
+    if file_size == 0 {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static("bytes */0"));
+        c.do_request(Method::PUT, session_url, Some(headers), Some(Body::from(Vec::new())))?;
+        return Ok(())
+    }
+
+    let mut offset = 0;
+    while offset < file_size {
+        let len = gcs_config.chunk_size.min(file_size - offset);
+        let mut headers = HeaderMap::new();
+        let range = format!("bytes {}-{}/{}", offset, offset + len - 1, file_size);
+        headers.insert(CONTENT_RANGE, HeaderValue::from_str(&range)?);
+
+        let mut f = File::open(file_path)?;
+        f.seek(SeekFrom::Start(offset))?;
+        pb.set_position(offset);
+        let pr = ProgressReader::with_bar(f.take(len), pb.clone());
+
+        let resp = c.do_request(Method::PUT, session_url, Some(headers), Some(Body::sized(pr, len)))?;
+        if resp.status() == StatusCode::PERMANENT_REDIRECT {
+            offset = get_persisted_end(resp.headers())?;
+        } else {
+            return Ok(())
+        }
+    }
+    Err(UnexpectedResponseError.into())
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    utils::check_file_size_limit(file.size, Size::Custom(MAX_OBJECT_SIZE))?;
+
+    let gcs_config = load_config(cfg)?;
+    let name = format!("{}{}", utils::render_date_vars(&gcs_config.name_prefix), file.name);
+    let headers = make_headers(c, &gcs_config, s)?;
+
+    let file_size = file.size as u64;
+    let session_url = start_session(c, &gcs_config, &name, file_size, &headers)?;
+
+    let pb = new_progress_bar(file_size)?;
+    let res = upload_chunks(c, &gcs_config, &session_url, &file.path, file_size, &pb);
+    pb.finish();
+    res?;
+
+    let url = format!("{}/{}/{}", gcs_config.endpoint, gcs_config.bucket, utf8_percent_encode(&name, PATH_ENCODE_SET));
+    let mut result = UploadResult::new(url);
+    // Needed to delete the object, delete only matches these through the URL or --key.
+    result.management.insert("bucket".to_string(), gcs_config.bucket);
+    result.management.insert("name".to_string(), name);
+    Ok(result)
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let name = result.management.get("name").ok_or("upload has no gcs object name")?;
+    let bucket = result.management.get("bucket").ok_or("upload has no gcs bucket")?;
+
+    let gcs_config = load_config(cfg)?;
+    let mut s: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let headers = make_headers(c, &gcs_config, &mut s)?;
+
+    let url = format!("{}/storage/v1/b/{}/o/{}", gcs_config.endpoint, bucket, utf8_percent_encode(name, SEGMENT_ENCODE_SET));
+    c.do_request(Method::DELETE, &url, Some(headers), None).map_err(map_auth_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_range(range: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, HeaderValue::from_str(range).unwrap());
+        headers
+    }
+
+    #[test]
+    fn persisted_end() {
+        assert_eq!(get_persisted_end(&HeaderMap::new()).unwrap(), 0);
+        assert_eq!(get_persisted_end(&with_range("bytes=0-0")).unwrap(), 1);
+        assert_eq!(get_persisted_end(&with_range("bytes=0-8388607")).unwrap(), 8388608);
+        assert!(get_persisted_end(&with_range("bytes=0-")).is_err());
+        assert!(get_persisted_end(&with_range("garbage")).is_err());
+    }
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod gcs;
+mod oauth;
+mod structs;
+
+pub use gcs::{delete, run};
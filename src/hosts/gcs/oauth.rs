@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use chrono::Utc;
+use reqwest::StatusCode;
+use rsa::RsaPrivateKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::signature::{SignatureEncoding, Signer};
+use sha2::Sha256;
+use crate::client::UploaderClient;
+use crate::hosts::errors::InvalidAPIKeyError;
+use crate::hosts::gcs::structs::{JwtClaims, ServiceAccount, TokenResp};
+use crate::hosts::utils;
+
+const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_write";
+const JWT_HEADER: &str = r#"{"alg":"RS256","typ":"JWT"}"#;
+const TOKEN_LIFETIME_SECS: i64 = 3600;
+const JWT_BEARER_GRANT: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+// Tokens are refreshed this long before they expire, so one doesn't run out in the middle of a request.
+const EXPIRY_MARGIN_SECS: i64 = 300;
+
+pub struct AccessToken {
+    pub token: String,
+    // Unix time from when the token should no longer be used.
+    pub refresh_at: i64,
+}
+
+fn make_jwt(account: &ServiceAccount) -> Result<String, Box<dyn Error>> {
+    let now = Utc::now().timestamp();
+    let claims = JwtClaims {
+        iss: account.client_email.clone(),
+        scope: SCOPE.to_string(),
+        aud: account.token_uri.clone(),
+        iat: now,
+        exp: now + TOKEN_LIFETIME_SECS,
+    };
+
+    let signing_input = format!("{}.{}",
+        BASE64_URL_SAFE_NO_PAD.encode(JWT_HEADER),
+        BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_string(&claims)?));
+
+    let private_key = RsaPrivateKey::from_pkcs8_pem(&account.private_key)?;
+    let signature = SigningKey::<Sha256>::new(private_key).sign(signing_input.as_bytes());
+    Ok(format!("{}.{}", signing_input, BASE64_URL_SAFE_NO_PAD.encode(signature.to_bytes())))
+}
+
+// Trades a JWT signed with the service account's key for an access token.
+pub fn get_access_token(c: &mut UploaderClient, credentials_path: &Path) -> Result<AccessToken, Box<dyn Error>> {
+    let data = fs::read_to_string(credentials_path)?;
+    let account: ServiceAccount = serde_json::from_str(&data)?;
+
+    let fields: Vec<(String, String)> = vec![
+        ("grant_type".to_string(), JWT_BEARER_GRANT.to_string()),
+        ("assertion".to_string(), make_jwt(&account)?),
+    ];
+
+    let resp = match c.do_form_post(&account.token_uri, None, &fields) {
+        Ok(resp) => resp,
+        Err(err) => {
+            if let Some(StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED) = utils::get_error_status(err.as_ref()) {
+                return Err(InvalidAPIKeyError.into())
+            }
+            return Err(err)
+        },
+    };
+    let json_obj: TokenResp = resp.json()?;
+    let lifetime = json_obj.expires_in.unwrap_or(TOKEN_LIFETIME_SECS);
+    let token = AccessToken {
+        token: json_obj.access_token,
+        refresh_at: Utc::now().timestamp() + lifetime - EXPIRY_MARGIN_SECS,
+    };
+    Ok(token)
+}
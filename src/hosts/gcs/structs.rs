@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+// The parts of a service account key file needed to get a token.
+#[derive(Deserialize)]
+pub struct ServiceAccount {
+    pub client_email: String,
+    pub private_key: String,
+    pub token_uri: String,
+}
+
+#[derive(Serialize)]
+pub struct JwtClaims {
+    pub iss: String,
+    pub scope: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Deserialize)]
+pub struct TokenResp {
+    pub access_token: String,
+    // Seconds, usually 3599.
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectMetadata {
+    pub name: String,
+    pub content_type: String,
+}
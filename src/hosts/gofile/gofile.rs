@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 use std::error::Error;
-use std::path::PathBuf;
 use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue, REFERER};
 use crate::client::UploaderClient;
 use crate::hosts::errors::{BadAPIResponseError};
 use crate::hosts::gofile::structs::*;
+use crate::hosts::structs::UploadResult;
 use crate::hosts::utils;
+use crate::structs::UploadFile;
 
 const API_BASE: &str = "https://api.gofile.io/";
 const BASE_URL: &str = "https://gofile.io/";
@@ -54,7 +55,7 @@ fn create_folder(c: &mut UploaderClient, token: &str, root_folder_id: String) ->
     }
 
     let folder_id = json_obj.data.id;
-    set_folder_attributes(c, &folder_id, &token)?;
+    set_folder_attributes(c, &folder_id, token)?;
 
     Ok(folder_id)
 }
@@ -79,7 +80,7 @@ fn set_folder_attributes(c: &mut UploaderClient, folder_id: &str, token: &str) -
     Ok(())
 }
 
-fn upload(c: &mut UploaderClient, server: &str, token: &str, folder_id: String, file_path: &PathBuf) -> Result<String, Box<dyn Error>> {
+fn upload(c: &mut UploaderClient, server: &str, token: &str, folder_id: String, file: &UploadFile) -> Result<UploadData, Box<dyn Error>> {
     let url = format!("https://{}.gofile.io/contents/uploadFile", server);
 
     let mut headers = HeaderMap::new();
@@ -90,13 +91,13 @@ fn upload(c: &mut UploaderClient, server: &str, token: &str, folder_id: String,
         ("folderId".to_string(), folder_id),
     ];
 
-    let resp = c.multipart_upload(&url, "file".to_string(), file_path, Some(headers), Some(fields), None)?;
+    let resp = c.multipart_upload(&url, "file".to_string(), file, Some(headers), Some(fields), None)?;
     let json_obj: UploadResp = resp.json()?;
     if json_obj.status != "ok" {
         return Err(BadAPIResponseError.into())
     }
 
-    Ok(json_obj.data.download_page)
+    Ok(json_obj.data)
 }
 
 // fn get_value(m: HashMap<String, String>, key: &str) -> Result<String, Box<dyn Error>> {
@@ -112,7 +113,7 @@ fn upload(c: &mut UploaderClient, server: &str, token: &str, folder_id: String,
 //     Ok(value.to_string())
 // }
 
-pub fn run(c: &mut UploaderClient, file_path: &PathBuf, _file_size: usize, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<String, Box<dyn Error>> {
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
     let mut token = utils::get_session_val(s, HOST,  "token");
     let mut root_folder = utils::get_session_val(s, HOST,  "root_folder");
 
@@ -139,7 +140,33 @@ pub fn run(c: &mut UploaderClient, file_path: &PathBuf, _file_size: usize, cfg:
         folder_id = create_folder(c, &token, root_folder)?;
     }
 
-    let file_url = upload(c, &server, &token, folder_id, file_path)?;
+    let upload_data = upload(c, &server, &token, folder_id, file)?;
 
-    Ok(file_url)
+    let mut result = UploadResult::new(upload_data.download_page);
+    result.management.insert("file_id".to_string(), upload_data.id);
+    result.management.insert("folder_id".to_string(), upload_data.parent_folder);
+    result.management.insert("token".to_string(), token.trim_start_matches("Bearer ").to_string());
+    Ok(result)
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, _cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let file_id = result.management.get("file_id").ok_or("upload has no gofile file id")?;
+    let token = result.management.get("token").ok_or("upload has no gofile token")?;
+
+    let delete_data = DeleteContentsDelete {
+        contents_id: file_id.to_string(),
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(REFERER, HeaderValue::from_static(BASE_URL));
+    headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token))?);
+
+    let url = format!("{}contents", API_BASE);
+    let resp = c.do_delete(&url, Some(headers), Some(&delete_data))?;
+    let json_obj: StatusResp = resp.json()?;
+    if json_obj.status != "ok" {
+        return Err(BadAPIResponseError.into())
+    }
+
+    Ok(())
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod gofile;
 mod structs;
 
-pub use gofile::run;
\ No newline at end of file
+pub use gofile::{delete, run};
\ No newline at end of file
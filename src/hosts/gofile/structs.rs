@@ -28,6 +28,17 @@ pub struct FolderAttributesPut {
     pub attribute_value: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteContentsDelete {
+    pub contents_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct StatusResp {
+    pub status: String,
+}
+
 #[derive(Deserialize)]
 pub struct CreateFolderData {
     pub id: String,
@@ -55,6 +66,9 @@ pub struct AccountResp {
 #[serde(rename_all = "camelCase")]
 pub struct UploadData {
     pub download_page: String,
+    #[serde(alias = "fileId")]
+    pub id: String,
+    pub parent_folder: String,
 }
 #[derive(Deserialize)]
 pub struct UploadResp {
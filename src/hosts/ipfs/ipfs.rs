@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+use reqwest::Method;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use url::Url;
+use crate::client::UploaderClient;
+use crate::hosts::ipfs::structs::{AddResp, StatResp};
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const DEFAULT_API_URL: &str = "http://127.0.0.1:5001";
+const DEFAULT_GATEWAYS: &str = "https://ipfs.io";
+const HOST: &str = "ipfs";
+// Batches are assembled here in the node's MFS before being wrapped into one directory.
+const MFS_ROOT: &str = "/uploader-rs";
+
+// Kubo treats Mozilla user agents as browsers and rejects their API calls without an allowed origin.
+fn make_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static("uploader-rs"));
+    headers
+}
+
+fn make_api_url(ipfs_cfg: &HashMap<String, String>, command: &str, args: &[(&str, &str)]) -> Result<Url, Box<dyn Error>> {
+    let api_url = utils::get_config_val(ipfs_cfg, "api_url", false)?;
+    let api_url = if api_url.is_empty() { DEFAULT_API_URL } else { api_url.as_str() };
+
+    let mut url = Url::parse(api_url)?;
+    url.path_segments_mut()
+        .map_err(|_| "invalid ipfs api url")?
+        .pop_if_empty()
+        .extend(["api", "v0"])
+        .extend(command.split('/'));
+    if !args.is_empty() {
+        url.query_pairs_mut().extend_pairs(args);
+    }
+    Ok(url)
+}
+
+// Every Kubo RPC call is a POST, even the read-only ones.
+fn call(c: &mut UploaderClient, ipfs_cfg: &HashMap<String, String>, command: &str, args: &[(&str, &str)]) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+    let url = make_api_url(ipfs_cfg, command, args)?;
+    c.do_post::<()>(url.as_str(), Some(make_headers()), None)
+}
+
+fn get_gateways(ipfs_cfg: &HashMap<String, String>) -> Result<Vec<String>, Box<dyn Error>> {
+    let gateways = utils::get_config_val(ipfs_cfg, "gateways", false)?;
+    let gateways = if gateways.is_empty() { DEFAULT_GATEWAYS } else { gateways.as_str() };
+    Ok(gateways
+        .split(',')
+        .map(|gw| gw.trim().trim_end_matches('/').to_string())
+        .filter(|gw| !gw.is_empty())
+        .collect())
+}
+
+// Path style gateways get /ipfs/<cid> appended, subdomain ones put <cid> where it goes, e.g. https://<cid>.ipfs.dweb.link.
+fn make_gateway_url(gateway: &str, cid: &str) -> String {
+    if gateway.contains("<cid>") {
+        gateway.replace("<cid>", cid)
+    } else {
+        format!("{}/ipfs/{}", gateway, cid)
+    }
+}
+
+fn make_file_url(gateway: &str, cid: &str, filename: &str) -> Result<String, Box<dyn Error>> {
+    let mut url = Url::parse(&make_gateway_url(gateway, cid))?;
+    // Gateways use this as the download's name, since the cid alone has none.
+    url.query_pairs_mut().append_pair("filename", filename);
+    Ok(url.to_string())
+}
+
+fn get_mfs_dir(s: &mut HashMap<String, HashMap<String, String>>) -> Result<String, Box<dyn Error>> {
+    let mfs_dir = utils::get_session_val(s, HOST, "mfs_dir");
+    if !mfs_dir.is_empty() {
+        return Ok(mfs_dir)
+    }
+
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let mfs_dir = format!("{}/{}", MFS_ROOT, stamp);
+    utils::set_session_val(s, HOST, "mfs_dir", &mfs_dir);
+    Ok(mfs_dir)
+}
+
+// Links the added file into this run's MFS dir, keeping its layout relative to the -d root.
+fn add_to_mfs_dir(c: &mut UploaderClient, ipfs_cfg: &HashMap<String, String>, file: &UploadFile, cid: &str, s: &mut HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let mut mfs_path = get_mfs_dir(s)?;
+    for dir in utils::get_dir_segments(&file.rel_dir) {
+        mfs_path.push('/');
+        mfs_path.push_str(&dir);
+    }
+    call(c, ipfs_cfg, "files/mkdir", &[("arg", &mfs_path), ("parents", "true")])?;
+
+    mfs_path.push('/');
+    mfs_path.push_str(&file.name);
+    call(c, ipfs_cfg, "files/cp", &[("arg", &format!("/ipfs/{}", cid)), ("arg", &mfs_path)])?;
+    Ok(())
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let ipfs_cfg = utils::get_section_or_empty(cfg, HOST);
+    let pin = utils::get_config_val(&ipfs_cfg, "pin", false)? == "y";
+    let cid_version = utils::get_config_val(&ipfs_cfg, "cid_version", false)?;
+
+    let mut args = vec![("pin", if pin { "true" } else { "false" })];
+    if !cid_version.is_empty() {
+        args.push(("cid-version", cid_version.as_str()));
+    }
+    let url = make_api_url(&ipfs_cfg, "add", &args)?;
+
+    let resp = c.multipart_request(Method::POST, url.as_str(), "file".to_string(), file, Some(make_headers()), None, None)?;
+    let add_resp: AddResp = resp.json()?;
+    let cid = add_resp.hash;
+
+    if utils::get_config_val(&ipfs_cfg, "wrap", false)? == "y" {
+        add_to_mfs_dir(c, &ipfs_cfg, file, &cid, s)?;
+    }
+
+    let gateways = get_gateways(&ipfs_cfg)?;
+    let mut urls = gateways
+        .iter()
+        .map(|gw| make_file_url(gw, &cid, &file.name))
+        .collect::<Result<Vec<String>, Box<dyn Error>>>()?;
+    if urls.is_empty() {
+        return Err("no ipfs gateways configured".into())
+    }
+
+    let mut result = UploadResult::new(urls.remove(0));
+    result.mirrors = urls;
+    result.management.insert("cid".to_string(), cid);
+    Ok(result)
+}
+
+// Wraps the batch into one directory cid, then drops the MFS copy as the cid stays valid without it.
+pub fn finish(c: &mut UploaderClient, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<Option<String>, Box<dyn Error>> {
+    let ipfs_cfg = utils::get_section_or_empty(cfg, HOST);
+    let mfs_dir = utils::get_session_val(s, HOST, "mfs_dir");
+    if mfs_dir.is_empty() {
+        return Ok(None)
+    }
+    utils::set_session_val(s, HOST, "mfs_dir", "");
+
+    let resp = call(c, &ipfs_cfg, "files/stat", &[("arg", &mfs_dir)])?;
+    let stat_resp: StatResp = resp.json()?;
+    let dir_cid = stat_resp.hash;
+
+    if utils::get_config_val(&ipfs_cfg, "pin", false)? == "y" {
+        call(c, &ipfs_cfg, "pin/add", &[("arg", &dir_cid)])?;
+    }
+    call(c, &ipfs_cfg, "files/rm", &[("arg", &mfs_dir), ("recursive", "true")])?;
+
+    let gateways = get_gateways(&ipfs_cfg)?;
+    let gateway = gateways.first().ok_or("no ipfs gateways configured")?;
+    Ok(Some(format!("{}/", make_gateway_url(gateway, &dir_cid))))
+}
+
+// Unpinning is as close to deleting as IPFS gets, the node drops the blocks on its next gc.
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let cid = result.management.get("cid").ok_or("upload has no cid")?;
+
+    let ipfs_cfg = utils::get_section_or_empty(cfg, HOST);
+    call(c, &ipfs_cfg, "pin/rm", &[("arg", cid)])?;
+    Ok(())
+}
@@ -0,0 +1,5 @@
+#[allow(clippy::module_inception)]
+pub mod ipfs;
+mod structs;
+
+pub use ipfs::{delete, finish, run};
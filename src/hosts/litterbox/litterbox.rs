@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::error::Error;
+use chrono::{SecondsFormat, TimeDelta, Utc};
+use crate::client::UploaderClient;
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const API_URL: &str = "https://litterbox.catbox.moe/resources/internals/api.php";
+const HOST: &str = "litterbox";
+const DEFAULT_TIME: &str = "1h";
+
+fn parse_time(time: &str) -> Result<TimeDelta, Box<dyn Error>> {
+    let hours = match time {
+        "1h" => 1,
+        "12h" => 12,
+        "24h" => 24,
+        "72h" => 72,
+        _ => return Err(format!("invalid litterbox time: {}, expected 1h, 12h, 24h or 72h", time).into()),
+    };
+    Ok(TimeDelta::hours(hours))
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, _s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    utils::check_file_size_limit(file.size, Size::GB1)?;
+
+    let litterbox_cfg = utils::get_section_or_empty(cfg, HOST);
+    let mut time = utils::get_config_val(&litterbox_cfg, "time", false)?;
+    if time.is_empty() {
+        time = DEFAULT_TIME.to_string();
+    }
+    let lifetime = parse_time(&time)?;
+
+    let fields: Vec<(String, String)> = vec![
+        ("reqtype".to_string(), "fileupload".to_string()),
+        ("time".to_string(), time),
+    ];
+
+    let resp = c.multipart_upload(API_URL, "fileToUpload".to_string(), file, None, Some(fields), None)?;
+    let text = resp.text()?.trim().to_string();
+    if !text.starts_with("https://") {
+        return Err(format!("api reported failure: {}", text).into())
+    }
+
+    let mut result = UploadResult::new(text);
+    let expires = Utc::now() + lifetime;
+    result.expires = Some(expires.to_rfc3339_opts(SecondsFormat::Secs, true));
+    Ok(result)
+}
@@ -1,7 +1,21 @@
+pub mod azblob;
+pub mod b2;
+pub mod catbox;
+pub mod custom;
 pub mod fileio;
+pub mod ftp;
+pub mod gcs;
 pub mod gofile;
+pub mod ipfs;
+pub mod litterbox;
+pub mod nullpointer;
 mod utils;
 pub mod errors;
-mod sizes;
+pub mod sizes;
 pub mod enums;
 pub mod pixeldrain;
+pub mod s3;
+pub mod sftp;
+pub mod structs;
+pub mod transfersh;
+pub mod webdav;
@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod nullpointer;
+
+pub use nullpointer::{delete, run};
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::error::Error;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use crate::client::UploaderClient;
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const DEFAULT_BASE_URL: &str = "https://0x0.st";
+const HOST: &str = "nullpointer";
+
+// 0x0.st defaults, self-hosted instances can change them in their config.
+const DEFAULT_MAX_SIZE: usize = 536_870_912;
+const DEFAULT_MIN_AGE_DAYS: f64 = 30.0;
+const DEFAULT_MAX_AGE_DAYS: f64 = 365.0;
+
+fn get_config_num<T: std::str::FromStr>(np_cfg: &HashMap<String, String>, key: &str, default: T) -> Result<T, Box<dyn Error>> {
+    let value = utils::get_config_val(np_cfg, key, false)?;
+    if value.is_empty() {
+        return Ok(default)
+    }
+    value.parse::<T>()
+        .map_err(|_| format!("invalid {} value: {}", key, value).into())
+}
+
+// Bigger files are kept for less time, scaling from max_age down to min_age.
+fn get_retention(file_size: usize, max_size: usize, min_age: f64, max_age: f64) -> TimeDelta {
+    let ratio = file_size as f64 / max_size as f64;
+    let days = min_age + (min_age - max_age) * (ratio - 1.0).powi(3);
+    TimeDelta::seconds((days * 86_400.0) as i64)
+}
+
+fn get_expiry(resp_expires: Option<&str>, requested_hours: Option<i64>, retention: TimeDelta) -> String {
+    let from_header = resp_expires
+        .and_then(|v| v.parse::<i64>().ok())
+        .and_then(DateTime::from_timestamp_millis);
+
+    let expires = from_header.unwrap_or_else(|| {
+        let lifetime = match requested_hours {
+            Some(hours) => TimeDelta::hours(hours).min(retention),
+            None => retention,
+        };
+        Utc::now() + lifetime
+    });
+
+    expires.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, _s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let np_cfg = utils::get_section_or_empty(cfg, HOST);
+
+    let max_size = get_config_num(&np_cfg, "max_size", DEFAULT_MAX_SIZE)?;
+    utils::check_file_size_limit(file.size, Size::Custom(max_size))?;
+
+    let mut base_url = utils::get_config_val(&np_cfg, "base_url", false)?;
+    if base_url.is_empty() {
+        base_url = DEFAULT_BASE_URL.to_string();
+    }
+
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    let expires = utils::get_config_val(&np_cfg, "expires", false)?;
+    let requested_hours = if expires.is_empty() {
+        None
+    } else {
+        let hours = expires.parse::<i64>()
+            .map_err(|_| format!("invalid expires value: {}", expires))?;
+        fields.push(("expires".to_string(), expires));
+        Some(hours)
+    };
+
+    let secret = utils::get_config_val(&np_cfg, "secret", false)?;
+    if secret == "y" {
+        fields.push(("secret".to_string(), String::new()));
+    }
+
+    let resp = c.multipart_upload(&base_url, "file".to_string(), file, None, Some(fields), None)?;
+    let headers = resp.headers().clone();
+    let text = resp.text()?.trim().to_string();
+    if !text.starts_with("http") {
+        return Err(format!("api reported failure: {}", text).into())
+    }
+
+    let min_age = get_config_num(&np_cfg, "min_age", DEFAULT_MIN_AGE_DAYS)?;
+    let max_age = get_config_num(&np_cfg, "max_age", DEFAULT_MAX_AGE_DAYS)?;
+    let retention = get_retention(file.size, max_size, min_age, max_age);
+    let resp_expires = headers.get("X-Expires").and_then(|v| v.to_str().ok());
+
+    let mut result = UploadResult::new(text);
+    result.expires = Some(get_expiry(resp_expires, requested_hours, retention));
+
+    if let Some(token) = headers.get("X-Token").and_then(|v| v.to_str().ok()) {
+        result.management.insert("token".to_string(), token.to_string());
+    }
+    Ok(result)
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, _cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let token = result.management.get("token").ok_or("upload has no management token")?;
+
+    let fields: Vec<(String, String)> = vec![
+        ("token".to_string(), token.to_string()),
+        ("delete".to_string(), String::new()),
+    ];
+
+    c.do_form_post(&result.url, None, &fields)?;
+    Ok(())
+}
@@ -1,4 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod pixeldrain;
 mod structs;
 
-pub use pixeldrain::run;
\ No newline at end of file
+pub use pixeldrain::{delete, finish, run};
@@ -1,65 +1,200 @@
 use std::collections::HashMap;
 use std::error::Error;
-use std::path::PathBuf;
-use regex::Regex;
-use reqwest::header::{HeaderMap, HeaderValue, REFERER};
+use indicatif::HumanBytes;
+use reqwest::{Method, StatusCode};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue, REFERER};
+use url::Url;
 use crate::client::UploaderClient;
-use crate::hosts::pixeldrain::structs::UploadResp;
+use crate::hosts::errors::{BadAPIResponseError, InvalidAPIKeyError, UnexpectedResponseError};
+use crate::hosts::pixeldrain::structs::*;
+use crate::hosts::structs::UploadResult;
 use crate::hosts::sizes::Size;
 use crate::hosts::utils;
+use crate::structs::UploadFile;
 
 const BASE_URL: &str = "https://pixeldrain.com/";
 const HOST: &str = "pixeldrain";
+const DEFAULT_LIST_TITLE: &str = "uploader-rs";
 
-// User info endpoint please :p.
-fn get_size_limit(c: &mut UploaderClient, cookies: &HashMap<String, String>) -> Result<String, Box<dyn std::error::Error>> {
-    let url = format!("{}user", BASE_URL);
-
-    let resp = c.do_get(&url, None, Some(cookies))?;
-    let html = resp.text()?;
+// The api takes the key as the basic auth password with an empty username.
+fn make_headers(c: &UploaderClient, api_key: &str) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    headers.insert(REFERER, HeaderValue::from_static(BASE_URL));
+    headers.insert(AUTHORIZATION, c.basic_auth_header("", api_key)?);
+    Ok(headers)
+}
 
-    let re = Regex::new(r#""file_size_limit":(\d+)"#)?;
+fn get_user_info(c: &mut UploaderClient, api_key: &str) -> Result<UserResp, Box<dyn Error>> {
+    let url = format!("{}api/user", BASE_URL);
+    let headers = make_headers(c, api_key)?;
 
-    if let Some(capture) = re.captures(&html) {
-        if let Some(m) = capture.get(1) {
-            let size_limit = m.as_str().to_string();
-            return Ok(size_limit)
+    let resp = match c.do_get(&url, Some(headers), None) {
+        Ok(resp) => resp,
+        Err(err) => {
+            if let Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) = utils::get_error_status(err.as_ref()) {
+                return Err(InvalidAPIKeyError.into())
+            }
+            return Err(err)
         }
-    }
+    };
 
-    Err("failed to extract file size limit from html".into())
+    let data = resp.text()?;
+    let json_obj: UserResp = serde_json::from_str(&data)
+        .map_err(|_| UnexpectedResponseError)?;
+    Ok(json_obj)
 }
 
-fn upload(c: &mut UploaderClient, file_path: &PathBuf, cookies: &HashMap<String, String>) -> Result<String, Box<dyn Error>> {
-    let url = format!("{}api/file", BASE_URL);
+fn print_user_info(user_info: &UserResp) {
+    let quota = if user_info.subscription.storage_space < 0 {
+        "unlimited".to_string()
+    } else {
+        HumanBytes(user_info.subscription.storage_space as u64).to_string()
+    };
 
-    let mut headers = HeaderMap::new();
-    headers.insert(REFERER, HeaderValue::from_static(BASE_URL));
+    println!(
+        "Signed in as {} ({}), storage used: {} of {}.",
+        user_info.username,
+        user_info.subscription.name,
+        HumanBytes(user_info.storage_space_used as u64),
+        quota,
+    );
+}
 
-    let resp = c.multipart_upload(&url, "file".to_string(), file_path, Some(headers), None, Some(cookies))?;
+// Streamed as a raw body, the PUT endpoint doesn't need to know the length up front.
+fn upload(c: &mut UploaderClient, file: &UploadFile, api_key: &str) -> Result<String, Box<dyn Error>> {
+    let mut url = Url::parse(&format!("{}api/file", BASE_URL))?;
+    url.path_segments_mut()
+        .map_err(|_| "failed to build upload url")?
+        .push(&file.name);
+
+    let headers = make_headers(c, api_key)?;
+    let resp = c.chunked_upload(Method::PUT, url.as_str(), &file.path, Some(headers))?;
     let json_obj: UploadResp = resp.json()?;
+    Ok(json_obj.id)
+}
+
+// Fallback for setups where the put endpoint is unavailable.
+fn multipart_upload(c: &mut UploaderClient, file: &UploadFile, api_key: &str) -> Result<String, Box<dyn Error>> {
+    let url = format!("{}api/file", BASE_URL);
+    let headers = make_headers(c, api_key)?;
+
+    let resp = c.multipart_upload(&url, "file".to_string(), file, Some(headers), None, None)?;
+    let json_obj: UploadResp = resp.json()?;
+    Ok(json_obj.id)
+}
+
+fn create_list(c: &mut UploaderClient, api_key: &str, title: String, file_ids: &str) -> Result<String, Box<dyn Error>> {
+    let url = format!("{}api/list", BASE_URL);
+
+    let files: Vec<ListFile> = file_ids
+        .split(',')
+        .map(|id| ListFile { id: id.to_string() })
+        .collect();
+
+    let create_list_data = CreateListPost {
+        title,
+        anonymous: false,
+        files,
+    };
+
+    let headers = make_headers(c, api_key)?;
+    let resp = c.do_post(&url, Some(headers), Some(&create_list_data))?;
+    let json_obj: CreateListResp = resp.json()?;
+    if !json_obj.success {
+        return Err(BadAPIResponseError.into())
+    }
 
-    let url = format!("{}u/{}", BASE_URL, json_obj.id);
+    let url = format!("{}l/{}", BASE_URL, json_obj.id);
     Ok(url)
 }
 
-pub fn run(c: &mut UploaderClient, file_path: &PathBuf, file_size: usize, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<String, Box<dyn Error>> {
+// What a proxy or server without the PUT endpoint, or without chunked bodies, answers.
+fn is_put_unavailable(err: &(dyn Error + 'static)) -> bool {
+    matches!(
+        utils::get_error_status(err),
+        Some(StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::LENGTH_REQUIRED | StatusCode::NOT_IMPLEMENTED)
+    )
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
     let mut api_key = utils::get_session_val(s, HOST, "api_key");
     let mut size_limit = utils::get_session_val(s, HOST, "size_limit");
 
-    let mut cookies: HashMap<String, String> = HashMap::new();
-
+    let pd_config = utils::get_section_or_empty(cfg, HOST);
     if api_key.is_empty() {
-        let pd_config = utils::get_section_or_empty(cfg, HOST);
         api_key = utils::get_config_val(&pd_config, "api_key", true)?;
 
-        cookies.insert("pd_auth_key".to_string(), api_key);
-        size_limit = get_size_limit(c, &cookies)?;
+        let user_info = get_user_info(c, &api_key)?;
+        print_user_info(&user_info);
+
+        size_limit = user_info.subscription.file_size_limit.to_string();
+        utils::set_session_val(s, HOST, "api_key", &api_key);
         utils::set_session_val(s, HOST, "size_limit", &size_limit);
     }
 
     let size_limit_obj = Size::custom_from_str(&size_limit)?;
-    utils::check_file_size_limit(file_size, size_limit_obj)?;
-    let file_url = upload(c, file_path, &cookies)?;
-    Ok(file_url)
-}
\ No newline at end of file
+    utils::check_file_size_limit(file.size, size_limit_obj)?;
+
+    // Once the PUT endpoint has failed, the rest of the batch goes straight to multipart.
+    let use_multipart = utils::get_config_val(&pd_config, "multipart_upload", false)? == "y"
+        || utils::get_session_val(s, HOST, "put_unavailable") == "y";
+    let file_id = if use_multipart {
+        multipart_upload(c, file, &api_key)?
+    } else {
+        match upload(c, file, &api_key) {
+            Ok(file_id) => file_id,
+            Err(err) if is_put_unavailable(err.as_ref()) => {
+                println!("Pixeldrain PUT upload unavailable, retrying as multipart.\n{:?}", err);
+                utils::set_session_val(s, HOST, "put_unavailable", "y");
+                multipart_upload(c, file, &api_key)?
+            },
+            Err(err) => return Err(err),
+        }
+    };
+
+    let mut file_ids = utils::get_session_val(s, HOST, "file_ids");
+    if !file_ids.is_empty() {
+        file_ids.push(',');
+    }
+    file_ids.push_str(&file_id);
+    utils::set_session_val(s, HOST, "file_ids", &file_ids);
+
+    let file_url = format!("{}u/{}", BASE_URL, file_id);
+    let mut result = UploadResult::new(file_url);
+    result.management.insert("file_id".to_string(), file_id);
+    Ok(result)
+}
+
+// Bundles every file uploaded in this batch into a list.
+pub fn finish(c: &mut UploaderClient, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<Option<String>, Box<dyn Error>> {
+    let pd_config = utils::get_section_or_empty(cfg, HOST);
+    let list_enabled = utils::get_config_val(&pd_config, "create_list", false)?;
+    let file_ids = utils::get_session_val(s, HOST, "file_ids");
+    // Cleared even when no list gets made, watch mode runs a batch every round.
+    utils::set_session_val(s, HOST, "file_ids", "");
+
+    if list_enabled != "y" || file_ids.is_empty() {
+        return Ok(None)
+    }
+
+    let mut title = utils::get_config_val(&pd_config, "list_title", false)?;
+    if title.is_empty() {
+        title = DEFAULT_LIST_TITLE.to_string();
+    }
+
+    let api_key = utils::get_session_val(s, HOST, "api_key");
+    let list_url = create_list(c, &api_key, title, &file_ids)?;
+    Ok(Some(list_url))
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let file_id = result.management.get("file_id").ok_or("upload has no pixeldrain file id")?;
+
+    let pd_config = utils::get_section_or_empty(cfg, HOST);
+    let api_key = utils::get_config_val(&pd_config, "api_key", true)?;
+    let headers = make_headers(c, &api_key)?;
+
+    let url = format!("{}api/file/{}", BASE_URL, file_id);
+    c.do_delete::<()>(&url, Some(headers), None)?;
+    Ok(())
+}
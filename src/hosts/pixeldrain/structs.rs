@@ -5,7 +5,35 @@ pub struct UploadResp {
     pub id: String,
 }
 
+#[derive(Deserialize)]
+pub struct Subscription {
+    pub name: String,
+    pub file_size_limit: usize,
+    // Negative when the plan has no storage cap.
+    pub storage_space: i64,
+}
+
+#[derive(Deserialize)]
+pub struct UserResp {
+    pub username: String,
+    pub subscription: Subscription,
+    pub storage_space_used: usize,
+}
+
+#[derive(Serialize)]
+pub struct ListFile {
+    pub id: String,
+}
+
 #[derive(Serialize)]
-pub struct CreateFolderPost {
+pub struct CreateListPost {
+    pub title: String,
+    pub anonymous: bool,
+    pub files: Vec<ListFile>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateListResp {
+    pub success: bool,
     pub id: String,
-}
\ No newline at end of file
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod s3;
+mod sigv4;
+mod structs;
+
+pub use s3::{delete, run};
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use chrono::{SecondsFormat, TimeDelta, Utc};
+use indicatif::ProgressBar;
+use mime_guess::MimeGuess;
+use reqwest::blocking::Body;
+use reqwest::header::{CONTENT_TYPE, ETAG, HeaderValue};
+use reqwest::Method;
+use url::Url;
+use crate::client::structs::{new_progress_bar, ProgressReader};
+use crate::client::UploaderClient;
+use crate::hosts::s3::sigv4::{self, Credentials, UNSIGNED_PAYLOAD};
+use crate::hosts::s3::structs::*;
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const HOST: &str = "s3";
+const DEFAULT_REGION: &str = "us-east-1";
+const MAX_OBJECT_SIZE: usize = 5_000_000_000_000;
+const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+const DEFAULT_PART_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+const DEFAULT_PRESIGN_LIFETIME: u64 = 3600;
+const MAX_PRESIGN_LIFETIME: u64 = 604_800;
+
+struct S3Config {
+    endpoint: Url,
+    bucket: String,
+    key_prefix: String,
+    path_style: bool,
+    part_size: u64,
+    multipart_threshold: u64,
+    presign: bool,
+    presign_lifetime: u64,
+    creds: Credentials,
+}
+
+fn get_config_num(s3_cfg: &HashMap<String, String>, key: &str, default: u64) -> Result<u64, Box<dyn Error>> {
+    let value = utils::get_config_val(s3_cfg, key, false)?;
+    if value.is_empty() {
+        return Ok(default)
+    }
+    value.parse::<u64>()
+        .map_err(|_| format!("invalid {} value: {}", key, value).into())
+}
+
+fn load_config(cfg: &HashMap<String, HashMap<String, String>>) -> Result<S3Config, Box<dyn Error>> {
+    let s3_cfg = utils::get_section_or_empty(cfg, HOST);
+
+    let endpoint = utils::get_config_val(&s3_cfg, "endpoint", true)?;
+    let mut region = utils::get_config_val(&s3_cfg, "region", false)?;
+    if region.is_empty() {
+        region = DEFAULT_REGION.to_string();
+    }
+
+    let part_size = get_config_num(&s3_cfg, "part_size", DEFAULT_PART_SIZE)?;
+    if part_size < MIN_PART_SIZE {
+        return Err(format!("part_size must be at least {} bytes", MIN_PART_SIZE).into())
+    }
+
+    let presign_lifetime = get_config_num(&s3_cfg, "presign_lifetime", DEFAULT_PRESIGN_LIFETIME)?;
+    if presign_lifetime > MAX_PRESIGN_LIFETIME {
+        return Err(format!("presign_lifetime can't exceed {} seconds", MAX_PRESIGN_LIFETIME).into())
+    }
+
+    let s3_config = S3Config {
+        endpoint: Url::parse(&endpoint)?,
+        bucket: utils::get_config_val(&s3_cfg, "bucket", true)?,
+        key_prefix: utils::get_config_val(&s3_cfg, "key_prefix", false)?,
+        path_style: utils::get_config_val(&s3_cfg, "path_style", false)? == "y",
+        part_size,
+        multipart_threshold: get_config_num(&s3_cfg, "multipart_threshold", part_size)?,
+        presign: utils::get_config_val(&s3_cfg, "presign", false)? == "y",
+        presign_lifetime,
+        creds: Credentials {
+            access_key: utils::get_config_val(&s3_cfg, "access_key", true)?,
+            secret_key: utils::get_config_val(&s3_cfg, "secret_key", true)?,
+            region,
+        },
+    };
+    Ok(s3_config)
+}
+
+fn object_url(s3_config: &S3Config, key: &str) -> Result<Url, Box<dyn Error>> {
+    let endpoint = &s3_config.endpoint;
+    let base_path = endpoint.path().trim_end_matches('/');
+    let encoded_key = sigv4::encode_path(key);
+
+    let url = if s3_config.path_style {
+        format!("{}://{}{}/{}/{}", endpoint.scheme(), authority(endpoint)?, base_path, s3_config.bucket, encoded_key)
+    } else {
+        format!("{}://{}.{}{}/{}", endpoint.scheme(), s3_config.bucket, authority(endpoint)?, base_path, encoded_key)
+    };
+    Ok(Url::parse(&url)?)
+}
+
+fn authority(url: &Url) -> Result<String, Box<dyn Error>> {
+    let host = url.host_str().ok_or("s3 endpoint has no host")?;
+    match url.port() {
+        Some(port) => Ok(format!("{}:{}", host, port)),
+        None => Ok(host.to_string()),
+    }
+}
+
+fn with_query(url: &Url, params: &[(String, String)]) -> Url {
+    let mut url = url.clone();
+    url.set_query(Some(&sigv4::canonical_query(params)));
+    url
+}
+
+fn get_mime(key: &str) -> Result<HeaderValue, Box<dyn Error>> {
+    let mime = MimeGuess::from_path(key).first_or_octet_stream().to_string();
+    Ok(HeaderValue::from_str(&mime)?)
+}
+
+fn put_object(c: &mut UploaderClient, s3_config: &S3Config, url: &Url, file_path: &PathBuf, file_size: u64) -> Result<(), Box<dyn Error>> {
+    let f = File::open(file_path)?;
+    let pr = ProgressReader::new(f, file_size)?;
+
+    let mut headers = sigv4::sign_request("PUT", url, UNSIGNED_PAYLOAD, &s3_config.creds)?;
+    headers.insert(CONTENT_TYPE, get_mime(url.path())?);
+
+    c.do_request(Method::PUT, url.as_str(), Some(headers), Some(Body::sized(pr, file_size)))?;
+    Ok(())
+}
+
+fn create_multipart(c: &mut UploaderClient, s3_config: &S3Config, url: &Url) -> Result<String, Box<dyn Error>> {
+    let url = with_query(url, &[("uploads".to_string(), String::new())]);
+
+    let mut headers = sigv4::sign_request("POST", &url, &sigv4::sha256_hex(b""), &s3_config.creds)?;
+    headers.insert(CONTENT_TYPE, get_mime(url.path())?);
+
+    let resp = c.do_request(Method::POST, url.as_str(), Some(headers), None)?;
+    let xml_obj: InitiateMultipartUploadResult = quick_xml::de::from_str(&resp.text()?)?;
+    Ok(xml_obj.upload_id)
+}
+
+fn upload_part(c: &mut UploaderClient, s3_config: &S3Config, url: &Url, upload_id: &str, part_number: u64, reader: ProgressReader<impl Read + Send + 'static>, len: u64) -> Result<String, Box<dyn Error>> {
+    let url = with_query(url, &[
+        ("partNumber".to_string(), part_number.to_string()),
+        ("uploadId".to_string(), upload_id.to_string()),
+    ]);
+
+    let headers = sigv4::sign_request("PUT", &url, UNSIGNED_PAYLOAD, &s3_config.creds)?;
+    let resp = c.do_request(Method::PUT, url.as_str(), Some(headers), Some(Body::sized(reader, len)))?;
+
+    let etag = resp.headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .ok_or("s3 part upload returned no etag")?;
+    Ok(etag.to_string())
+}
+
+fn complete_multipart(c: &mut UploaderClient, s3_config: &S3Config, url: &Url, upload_id: &str, parts: Vec<CompletedPart>) -> Result<(), Box<dyn Error>> {
+    let url = with_query(url, &[("uploadId".to_string(), upload_id.to_string())]);
+    let body = quick_xml::se::to_string(&CompleteMultipartUpload { parts })?;
+
+    let headers = sigv4::sign_request("POST", &url, &sigv4::sha256_hex(body.as_bytes()), &s3_config.creds)?;
+    let resp = c.do_request(Method::POST, url.as_str(), Some(headers), Some(Body::from(body)))?;
+
+    // Failures can still come back as a 200 with an error document.
+    let text = resp.text()?;
+    if text.contains("<Error>") {
+        return Err(format!("s3 failed to complete multipart upload: {}", text).into())
+    }
+    Ok(())
+}
+
+fn abort_multipart(c: &mut UploaderClient, s3_config: &S3Config, url: &Url, upload_id: &str) -> Result<(), Box<dyn Error>> {
+    let url = with_query(url, &[("uploadId".to_string(), upload_id.to_string())]);
+    let headers = sigv4::sign_request("DELETE", &url, &sigv4::sha256_hex(b""), &s3_config.creds)?;
+    c.do_request(Method::DELETE, url.as_str(), Some(headers), None)?;
+    Ok(())
+}
+
+fn upload_parts(c: &mut UploaderClient, s3_config: &S3Config, url: &Url, upload_id: &str, file_path: &PathBuf, file_size: u64, pb: &ProgressBar) -> Result<Vec<CompletedPart>, Box<dyn Error>> {
+    // Parts are capped at 10,000, so huge files get bigger parts.
+    let part_size = s3_config.part_size.max(file_size.div_ceil(MAX_PARTS));
+    let part_total = file_size.div_ceil(part_size).max(1);
+
+    let mut parts: Vec<CompletedPart> = Vec::new();
+    for part_number in 1..=part_total {
+        let offset = (part_number - 1) * part_size;
+        let len = part_size.min(file_size - offset);
+
+        let mut f = File::open(file_path)?;
+        f.seek(SeekFrom::Start(offset))?;
+        let pr = ProgressReader::with_bar(f.take(len), pb.clone());
+
+        let etag = upload_part(c, s3_config, url, upload_id, part_number, pr, len)?;
+        parts.push(CompletedPart { part_number, etag });
+    }
+    Ok(parts)
+}
+
+fn multipart_object(c: &mut UploaderClient, s3_config: &S3Config, url: &Url, file_path: &PathBuf, file_size: u64) -> Result<(), Box<dyn Error>> {
+    let upload_id = create_multipart(c, s3_config, url)?;
+
+    let pb = new_progress_bar(file_size)?;
+    let parts = upload_parts(c, s3_config, url, &upload_id, file_path, file_size, &pb);
+    pb.finish();
+
+    let res = parts.and_then(|parts| complete_multipart(c, s3_config, url, &upload_id, parts));
+    if res.is_err() {
+        if let Err(err) = abort_multipart(c, s3_config, url, &upload_id) {
+            println!("Failed to abort multipart upload.\n{:?}", err);
+        }
+    }
+    res
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, _s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    utils::check_file_size_limit(file.size, Size::Custom(MAX_OBJECT_SIZE))?;
+
+    let s3_config = load_config(cfg)?;
+    let key = format!("{}{}", utils::render_date_vars(&s3_config.key_prefix), file.name);
+    let url = object_url(&s3_config, &key)?;
+
+    let file_size = file.size as u64;
+    if file_size > s3_config.multipart_threshold {
+        multipart_object(c, &s3_config, &url, &file.path, file_size)?;
+    } else {
+        put_object(c, &s3_config, &url, &file.path, file_size)?;
+    }
+
+    let mut result = if s3_config.presign {
+        let presigned = sigv4::presign_get(&url, s3_config.presign_lifetime, &s3_config.creds)?;
+        let mut result = UploadResult::new(presigned.to_string());
+        let expires = Utc::now() + TimeDelta::seconds(s3_config.presign_lifetime as i64);
+        result.expires = Some(expires.to_rfc3339_opts(SecondsFormat::Secs, true));
+        result
+    } else {
+        UploadResult::new(url.to_string())
+    };
+
+    // Needed to delete the object, but shared by every upload so delete doesn't match on it.
+    result.management.insert("bucket".to_string(), s3_config.bucket);
+    result.management.insert("key".to_string(), key);
+    Ok(result)
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let key = result.management.get("key").ok_or("upload has no s3 key")?;
+    let bucket = result.management.get("bucket").ok_or("upload has no s3 bucket")?;
+
+    let mut s3_config = load_config(cfg)?;
+    s3_config.bucket = bucket.to_string();
+    let url = object_url(&s3_config, key)?;
+
+    let headers = sigv4::sign_request("DELETE", &url, &sigv4::sha256_hex(b""), &s3_config.creds)?;
+    c.do_request(Method::DELETE, url.as_str(), Some(headers), None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use crate::test_server::{self, Request, Response, TestServer};
+    use super::*;
+
+    const PART: usize = MIN_PART_SIZE as usize;
+
+    // A stand-in bucket that tags each part with its number, and fails the given part.
+    fn s3_server(fail_part: Option<u64>) -> TestServer {
+        TestServer::new(move |req: &Request| {
+            let query = req.path.split_once('?').map(|(_, q)| q).unwrap_or_default();
+            let part_number = query.split('&')
+                .find_map(|p| p.strip_prefix("partNumber="))
+                .and_then(|n| n.parse::<u64>().ok());
+
+            match (req.method.as_str(), query) {
+                ("POST", "uploads=") => Response::text(200, "<InitiateMultipartUploadResult><Bucket>my-bucket</Bucket><Key>big.bin</Key><UploadId>upload-1</UploadId></InitiateMultipartUploadResult>"),
+                ("PUT", _) if part_number.is_some() && part_number == fail_part => Response::text(500, "<Error><Code>InternalError</Code></Error>"),
+                ("PUT", _) => Response::new(200).header("ETag", &format!("\"etag-{}\"", part_number.unwrap_or(0))),
+                ("POST", _) => Response::text(200, "<CompleteMultipartUploadResult><ETag>\"etag-all\"</ETag></CompleteMultipartUploadResult>"),
+                ("DELETE", _) => Response::new(204),
+                _ => Response::new(400),
+            }
+        })
+    }
+
+    fn config(server: &TestServer, extra: &[(&str, &str)]) -> HashMap<String, HashMap<String, String>> {
+        let part_size = PART.to_string();
+        let mut values = vec![
+            ("endpoint", server.url.as_str()),
+            ("bucket", "my-bucket"),
+            ("path_style", "y"),
+            ("access_key", "AKIDEXAMPLE"),
+            ("secret_key", "secret"),
+            ("part_size", &part_size),
+        ];
+        values.extend_from_slice(extra);
+        test_server::config(HOST, &values)
+    }
+
+    fn big_file() -> (UploadFile, Vec<u8>) {
+        let data: Vec<u8> = (0..PART + 10).map(|i| (i % 251) as u8).collect();
+        (test_server::upload_file("big.bin", "big.bin", &data), data)
+    }
+
+    #[test]
+    fn multipart_upload() {
+        let server = s3_server(None);
+        let (file, data) = big_file();
+        let result = run(&mut UploaderClient::new().unwrap(), &file, &config(&server, &[]), &mut HashMap::new()).unwrap();
+
+        assert_eq!(server.paths(), [
+            "POST /my-bucket/big.bin?uploads=",
+            "PUT /my-bucket/big.bin?partNumber=1&uploadId=upload-1",
+            "PUT /my-bucket/big.bin?partNumber=2&uploadId=upload-1",
+            "POST /my-bucket/big.bin?uploadId=upload-1",
+        ]);
+        let requests = server.requests();
+        assert!(requests.iter().all(|r| r.header("Authorization").unwrap().starts_with("AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/")));
+        assert_eq!(requests[1].body, data[..PART]);
+        assert_eq!(requests[2].body, data[PART..]);
+
+        // The parts' etags go back as they were sent, quotes and all.
+        assert_eq!(
+            requests[3].text(),
+            "<CompleteMultipartUpload><Part><PartNumber>1</PartNumber><ETag>\"etag-1\"</ETag></Part><Part><PartNumber>2</PartNumber><ETag>\"etag-2\"</ETag></Part></CompleteMultipartUpload>",
+        );
+        assert_eq!(
+            requests[3].header("x-amz-content-sha256"),
+            Some(sigv4::sha256_hex(requests[3].body.as_slice()).as_str()),
+        );
+        assert_eq!(result.url, format!("{}/my-bucket/big.bin", server.url));
+        assert_eq!(result.management["key"], "big.bin");
+    }
+
+    #[test]
+    fn failed_part_aborts() {
+        let server = s3_server(Some(2));
+        let (file, _) = big_file();
+        assert!(run(&mut UploaderClient::new().unwrap(), &file, &config(&server, &[]), &mut HashMap::new()).is_err());
+
+        assert_eq!(server.paths(), [
+            "POST /my-bucket/big.bin?uploads=",
+            "PUT /my-bucket/big.bin?partNumber=1&uploadId=upload-1",
+            "PUT /my-bucket/big.bin?partNumber=2&uploadId=upload-1",
+            "DELETE /my-bucket/big.bin?uploadId=upload-1",
+        ]);
+    }
+
+    #[test]
+    fn small_file_presigned() {
+        let server = s3_server(None);
+        let file = test_server::upload_file("a.txt", "my file.txt", b"hello");
+        let cfg = config(&server, &[("presign", "y"), ("presign_lifetime", "600"), ("key_prefix", "up/")]);
+        let result = run(&mut UploaderClient::new().unwrap(), &file, &cfg, &mut HashMap::new()).unwrap();
+
+        assert_eq!(server.paths(), ["PUT /my-bucket/up/my%20file.txt"]);
+        assert_eq!(server.requests()[0].body, b"hello");
+        assert_eq!(server.requests()[0].header("Content-Type"), Some("text/plain"));
+
+        let url = Url::parse(&result.url).unwrap();
+        assert_eq!(url.path(), "/my-bucket/up/my%20file.txt");
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["X-Amz-Expires"], "600");
+        assert!(query.contains_key("X-Amz-Signature"));
+
+        let expires = DateTime::parse_from_rfc3339(result.expires.as_deref().unwrap()).unwrap();
+        let left = (expires.with_timezone(&Utc) - Utc::now()).num_seconds();
+        assert!((590..=600).contains(&left), "{}", left);
+    }
+
+    #[test]
+    fn presign_lifetime_limit() {
+        let server = s3_server(None);
+        let file = test_server::upload_file("a.txt", "a.txt", b"hello");
+        let cfg = config(&server, &[("presign", "y"), ("presign_lifetime", "604801")]);
+        assert!(run(&mut UploaderClient::new().unwrap(), &file, &cfg, &mut HashMap::new()).is_err());
+        assert!(server.requests().is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use ssh2::{CheckResult, KnownHostFileKind, Session, Sftp};
+use crate::client::structs::ProgressReader;
+use crate::client::UploaderClient;
+use crate::hosts::errors::InvalidAPIKeyError;
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const HOST: &str = "sftp";
+const DEFAULT_PORT: u16 = 22;
+
+fn get_known_hosts_path(sftp_cfg: &HashMap<String, String>) -> Result<PathBuf, Box<dyn Error>> {
+    let path = utils::get_config_val(sftp_cfg, "known_hosts", false)?;
+    if !path.is_empty() {
+        return Ok(PathBuf::from(path))
+    }
+
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .ok_or("couldn't find home directory for known_hosts, set known_hosts in config")?;
+    Ok(PathBuf::from(home).join(".ssh").join("known_hosts"))
+}
+
+fn check_host_key(sess: &Session, host: &str, port: u16, sftp_cfg: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+    if utils::get_config_val(sftp_cfg, "host_key_check", false)? == "n" {
+        return Ok(())
+    }
+
+    let mut known_hosts = sess.known_hosts()?;
+    known_hosts.read_file(&get_known_hosts_path(sftp_cfg)?, KnownHostFileKind::OpenSSH)?;
+
+    let (key, _) = sess.host_key().ok_or("server sent no host key")?;
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(format!("{} isn't in known_hosts, connect once with ssh to add it", host).into()),
+        CheckResult::Mismatch => Err(format!("host key for {} doesn't match known_hosts", host).into()),
+        CheckResult::Failure => Err("failed to check host key".into()),
+    }
+}
+
+// Tries the key file, then the password, then the ssh agent.
+fn authenticate(sess: &Session, sftp_cfg: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+    let username = utils::get_config_val(sftp_cfg, "username", true)?;
+    let key_path = utils::get_config_val(sftp_cfg, "key_path", false)?;
+    let password = utils::get_config_val(sftp_cfg, "password", false)?;
+
+    let res = if !key_path.is_empty() {
+        let passphrase = utils::get_config_val(sftp_cfg, "key_passphrase", false)?;
+        let passphrase = if passphrase.is_empty() { None } else { Some(passphrase.as_str()) };
+        sess.userauth_pubkey_file(&username, None, Path::new(&key_path), passphrase)
+    } else if !password.is_empty() {
+        sess.userauth_password(&username, &password)
+    } else {
+        sess.userauth_agent(&username)
+    };
+
+    if res.is_err() || !sess.authenticated() {
+        return Err(InvalidAPIKeyError.into())
+    }
+    Ok(())
+}
+
+fn connect(sftp_cfg: &HashMap<String, String>) -> Result<Sftp, Box<dyn Error>> {
+    let host = utils::get_config_val(sftp_cfg, "host", true)?;
+    let port = match utils::get_config_val(sftp_cfg, "port", false)?.as_str() {
+        "" => DEFAULT_PORT,
+        port => port.parse::<u16>()?,
+    };
+
+    let tcp = TcpStream::connect((host.as_str(), port))?;
+    let mut sess = Session::new()?;
+    sess.set_tcp_stream(tcp);
+    sess.handshake()?;
+
+    check_host_key(&sess, &host, port, sftp_cfg)?;
+    authenticate(&sess, sftp_cfg)?;
+    Ok(sess.sftp()?)
+}
+
+fn make_dirs(sftp: &Sftp, absolute: bool, dirs: &[String]) -> Result<(), Box<dyn Error>> {
+    for i in 1..=dirs.len() {
+        let path = utils::join_remote_path(absolute, &dirs[..i]);
+        if sftp.stat(Path::new(&path)).is_err() {
+            sftp.mkdir(Path::new(&path), 0o755)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run(_: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, _: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let sftp_cfg = utils::get_section_or_empty(cfg, HOST);
+
+    let max_size = utils::get_config_val(&sftp_cfg, "max_size", false)?;
+    if !max_size.is_empty() {
+        utils::check_file_size_limit(file.size, Size::custom_from_str(&max_size)?)?;
+    }
+
+    let public_url = utils::get_config_val(&sftp_cfg, "public_url", true)?;
+    let remote_dir = utils::get_config_val(&sftp_cfg, "remote_dir", false)?;
+    let absolute = remote_dir.starts_with('/');
+    let mut dirs = utils::get_remote_dirs(&remote_dir, &file.rel_dir);
+
+    let sftp = connect(&sftp_cfg)?;
+    make_dirs(&sftp, absolute, &dirs)?;
+
+    dirs.push(file.name.clone());
+    let remote_path = utils::join_remote_path(absolute, &dirs);
+    let mut remote_file = sftp.create(Path::new(&remote_path))?;
+    let mut reader = ProgressReader::new(File::open(&file.path)?, file.size as u64)?;
+    io::copy(&mut reader, &mut remote_file)?;
+
+    let mut segments = utils::get_dir_segments(&file.rel_dir);
+    segments.push(file.name.clone());
+    let mut result = UploadResult::new(utils::join_url_segments(&public_url, &segments)?);
+    result.management.insert("remote_path".to_string(), remote_path);
+    Ok(result)
+}
+
+pub fn delete(_: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let remote_path = result.management.get("remote_path").ok_or("upload has no remote path")?;
+
+    let sftp_cfg = utils::get_section_or_empty(cfg, HOST);
+    let sftp = connect(&sftp_cfg)?;
+    sftp.unlink(Path::new(remote_path))?;
+    Ok(())
+}
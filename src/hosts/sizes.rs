@@ -1,4 +1,5 @@
 #[allow(dead_code)]
+#[derive(Clone)]
 pub enum Size {
     MB50,
     MB100,
@@ -26,7 +27,7 @@ pub enum Size {
 }
 
 impl Size {
-    fn value(&self) -> usize {
+    pub fn value(&self) -> usize {
         match self {
             Size::MB50 => 50_000_000,
             Size::MB100 => 100_000_000,
@@ -45,7 +46,7 @@ impl Size {
             Size::GB25 => 25_000_000_000,
             Size::GB50 => 50_000_000_000,
             Size::GB100 => 100_000_000_000,
-            Size::GB150 => 150_0000_000_000,
+            Size::GB150 => 150_000_000_000,
             Size::GB200 => 200_000_000_000,
             Size::GB250 => 250_000_000_000,
             Size::GB300 => 300_000_000_000,
@@ -54,40 +55,75 @@ impl Size {
         }
     }
 
-    pub fn custom_from_str(s: &str) -> Result<Size, std::num::ParseIntError> {
-        let value = s.trim().parse::<usize>()?;
-        Ok(Size::Custom(value))
-    }
+    // Plain byte counts or human units, decimal like the variants above (500MB) or binary (512MiB).
+    pub fn custom_from_str(s: &str) -> Result<Size, String> {
+        let s = s.trim();
+        let split_at = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+        let (num, unit) = s.split_at(split_at);
+
+        let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" => 1_000,
+            "m" | "mb" => 1_000_000,
+            "g" | "gb" => 1_000_000_000,
+            "t" | "tb" => 1_000_000_000_000,
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            "tib" => 1 << 40,
+            _ => return Err(format!("invalid size unit: {}", unit)),
+        };
 
-    // impl Size {
-    //     fn value(&self) -> usize {
-    //         match self {
-    //             Size::MB50 => 52_428_800,
-    //             Size::MB100 => 104_857_600,
-    //             Size::MB125 => 131_072_000,
-    //             Size::MB128 => 134_217_728,
-    //             Size::MB200 => 209_715_200,
-    //             Size::MB250 => 262_144_000,
-    //             Size::MB300 => 314_572_800,
-    //             Size::MB500 => 536_870_912,
-    //             Size::GB1 => 1_073_741_824,
-    //             Size::GB2 => 2_147_483_648,
-    //             Size::GB5 => 5_368_709_120,
-    //             Size::GB10 => 10_737_418_240,
-    //             Size::GB15 => 16_106_127_360,
-    //             Size::GB20 => 21_474_836_480,
-    //             Size::GB25 => 26_843_545_600,
-    //             Size::GB50 => 53_687_091_200,
-    //             Size::GB100 => 107_374_182_400,
-    //             Size::GB150 => 161_061_273_600,
-    //             Size::GB200 => 214_748_364_800,
-    //             Size::GB250 => 268_435_456_000,
-    //             Size::GB300 => 322_122_547_200,
-    //             Size::GB500 => 536_870_912_000,
-    //         }
-    //     }
+        let num = num.trim();
+        let value = if let Ok(int) = num.parse::<u64>() {
+            int.checked_mul(multiplier)
+                .ok_or_else(|| format!("size too large: {}", s))?
+        } else {
+            let float = num.parse::<f64>()
+                .map_err(|_| format!("invalid size: {}", s))?;
+            if float < 0.0 {
+                return Err(format!("invalid size: {}", s))
+            }
+            let value = (float * multiplier as f64).round();
+            if value >= u64::MAX as f64 {
+                return Err(format!("size too large: {}", s))
+            }
+            value as u64
+        };
+        Ok(Size::Custom(value as usize))
+    }
 
     pub fn is_exceeded_by(&self, val: usize) -> bool {
         val > self.value()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(s: &str) -> usize {
+        Size::custom_from_str(s).unwrap().value()
+    }
+
+    #[test]
+    fn custom_sizes() {
+        assert_eq!(bytes("0"), 0);
+        assert_eq!(bytes("500"), 500);
+        assert_eq!(bytes("500b"), 500);
+        assert_eq!(bytes("500MB"), 500_000_000);
+        assert_eq!(bytes("2k"), 2_000);
+        assert_eq!(bytes(" 1.5 GB "), 1_500_000_000);
+        assert_eq!(bytes("512MiB"), 512 * 1024 * 1024);
+        assert_eq!(bytes("1tib"), 1 << 40);
+        assert_eq!(bytes("0.5KiB"), 512);
+        assert_eq!(bytes("1.4"), 1);
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        for s in ["", "MB", "abc", "12XB", "-1MB", "-1.5", "1.2.3MB", "20000000TB", "99999999999999999999", "18446744073709551615.5"] {
+            assert!(Size::custom_from_str(s).is_err(), "{} should be rejected", s);
+        }
+    }
+}
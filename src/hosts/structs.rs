@@ -0,0 +1,24 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct UploadResult {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    // Other urls serving the same file, e.g. further IPFS gateways.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    // Whatever the host needs to delete the upload later.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub management: BTreeMap<String, String>,
+}
+
+impl UploadResult {
+    pub fn new(url: String) -> Self {
+        UploadResult {
+            url,
+            ..Default::default()
+        }
+    }
+}
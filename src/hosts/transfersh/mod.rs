@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod transfersh;
+
+pub use transfersh::{delete, finish, run};
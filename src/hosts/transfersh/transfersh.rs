@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::error::Error;
+use chrono::{SecondsFormat, TimeDelta, Utc};
+use reqwest::{Method, StatusCode};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use url::Url;
+use crate::client::UploaderClient;
+use crate::hosts::errors::{InvalidAPIKeyError, UnexpectedResponseError};
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::structs::UploadFile;
+
+const DEFAULT_BASE_URL: &str = "https://transfer.sh";
+const HOST: &str = "transfersh";
+
+fn get_base_url(transfer_cfg: &HashMap<String, String>) -> Result<Url, Box<dyn Error>> {
+    let base_url = utils::get_config_val(transfer_cfg, "base_url", false)?;
+    let base_url = if base_url.is_empty() { DEFAULT_BASE_URL } else { base_url.as_str() };
+    Ok(Url::parse(base_url)?)
+}
+
+fn make_headers(c: &UploaderClient, transfer_cfg: &HashMap<String, String>) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+
+    let username = utils::get_config_val(transfer_cfg, "username", false)?;
+    if !username.is_empty() {
+        let password = utils::get_config_val(transfer_cfg, "password", false)?;
+        headers.insert(AUTHORIZATION, c.basic_auth_header(&username, &password)?);
+    }
+    Ok(headers)
+}
+
+fn get_max_days(transfer_cfg: &HashMap<String, String>) -> Result<Option<i64>, Box<dyn Error>> {
+    let max_days = utils::get_config_val(transfer_cfg, "max_days", false)?;
+    if max_days.is_empty() {
+        return Ok(None)
+    }
+    let days = max_days.parse::<i64>()
+        .map_err(|_| format!("invalid max_days value: {}", max_days))?;
+    Ok(Some(days))
+}
+
+// The "<token>/<filename>" part of a file url, used to refer to it in bundles.
+fn get_bundle_entry(file_url: &str) -> Result<String, Box<dyn Error>> {
+    let url = Url::parse(file_url)?;
+    let segments: Vec<&str> = url.path_segments()
+        .ok_or(UnexpectedResponseError)?
+        .collect();
+    if segments.len() < 2 {
+        return Err(UnexpectedResponseError.into())
+    }
+    // Commas separate the bundle's files.
+    Ok(segments[segments.len() - 2..].join("/").replace(',', "%2C"))
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let transfer_cfg = utils::get_section_or_empty(cfg, HOST);
+
+    let max_size = utils::get_config_val(&transfer_cfg, "max_size", false)?;
+    if !max_size.is_empty() {
+        utils::check_file_size_limit(file.size, Size::custom_from_str(&max_size)?)?;
+    }
+
+    let mut headers = make_headers(c, &transfer_cfg)?;
+    let max_downloads = utils::get_config_val(&transfer_cfg, "max_downloads", false)?;
+    if !max_downloads.is_empty() {
+        headers.insert("Max-Downloads", HeaderValue::from_str(&max_downloads)?);
+    }
+    let max_days = get_max_days(&transfer_cfg)?;
+    if let Some(days) = max_days {
+        headers.insert("Max-Days", HeaderValue::from_str(&days.to_string())?);
+    }
+
+    let mut url = get_base_url(&transfer_cfg)?;
+    url.path_segments_mut()
+        .map_err(|_| "invalid transfer.sh base url")?
+        .pop_if_empty()
+        .push(&file.name);
+
+    let resp = match c.put_upload(url.as_str(), &file.path, Some(headers)) {
+        Ok(resp) => resp,
+        Err(err) => {
+            if let Some(StatusCode::UNAUTHORIZED) = utils::get_error_status(err.as_ref()) {
+                return Err(InvalidAPIKeyError.into())
+            }
+            return Err(err)
+        },
+    };
+
+    let delete_url = resp.headers().get("X-Url-Delete")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let file_url = resp.text()?.trim().to_string();
+    if !file_url.starts_with("http") {
+        return Err(format!("api reported failure: {}", file_url).into())
+    }
+
+    let mut entries = utils::get_session_val(s, HOST, "bundle_entries");
+    if !entries.is_empty() {
+        entries.push(',');
+    }
+    entries.push_str(&get_bundle_entry(&file_url)?);
+    utils::set_session_val(s, HOST, "bundle_entries", &entries);
+
+    let mut result = UploadResult::new(file_url);
+    if let Some(days) = max_days {
+        let expires = Utc::now() + TimeDelta::days(days);
+        result.expires = Some(expires.to_rfc3339_opts(SecondsFormat::Secs, true));
+    }
+    if let Some(delete_url) = delete_url {
+        result.management.insert("delete_url".to_string(), delete_url);
+    }
+    Ok(result)
+}
+
+// Bundles are built by the server on download, so this only has to make the url.
+pub fn finish(_: &mut UploaderClient, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<Option<String>, Box<dyn Error>> {
+    let transfer_cfg = utils::get_section_or_empty(cfg, HOST);
+    let bundle = utils::get_config_val(&transfer_cfg, "bundle", false)?;
+    let entries = utils::get_session_val(s, HOST, "bundle_entries");
+    // The next batch starts empty, whether or not this one makes a bundle.
+    utils::set_session_val(s, HOST, "bundle_entries", "");
+
+    if bundle.is_empty() || entries.is_empty() {
+        return Ok(None)
+    }
+    if !["tar", "tar.gz", "zip"].contains(&bundle.as_str()) {
+        return Err(format!("invalid bundle format: {}, expected tar, tar.gz or zip", bundle).into())
+    }
+
+    let base_url = get_base_url(&transfer_cfg)?;
+    let bundle_url = format!("{}/({}).{}", base_url.as_str().trim_end_matches('/'), entries, bundle);
+    Ok(Some(bundle_url))
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let delete_url = result.management.get("delete_url").ok_or("upload has no delete url")?;
+
+    let transfer_cfg = utils::get_section_or_empty(cfg, HOST);
+    let headers = make_headers(c, &transfer_cfg)?;
+
+    c.do_request(Method::DELETE, delete_url, Some(headers), None)?;
+    Ok(())
+}
@@ -1,5 +1,9 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::path::Path;
+use chrono::Utc;
+use reqwest::{Error as ReqwestErr, StatusCode};
+use url::Url;
 use crate::hosts::errors::{FileSizeLimitError, SectionKeyMissingError, SectionValueEmptyError};
 use crate::hosts::sizes::Size;
 
@@ -26,7 +30,7 @@ pub fn get_session_val(s: &HashMap<String, HashMap<String, String>>, host: &str,
 }
 
 pub fn set_session_val(s: &mut HashMap<String, HashMap<String, String>>, host: &str, key: &str, value: &str) {
-    let inner_map = s.entry(host.to_string()).or_insert_with(HashMap::new);
+    let inner_map = s.entry(host.to_string()).or_default();
     inner_map.insert(key.to_string(), value.to_string());
 }
 
@@ -46,4 +50,57 @@ pub fn get_config_val(map: &HashMap<String, String>, key: &str, required: bool)
             None => Ok(String::new()),
         }
     }
-}
\ No newline at end of file
+}
+
+pub fn get_error_status(err: &(dyn Error + 'static)) -> Option<StatusCode> {
+    err.downcast_ref::<ReqwestErr>().and_then(|e| e.status())
+}
+
+// Fills the date, year, month and day vars of remote path templates.
+pub fn render_date_vars(template: &str) -> String {
+    let now = Utc::now();
+    template
+        .replace("<date>", &now.format("%Y-%m-%d").to_string())
+        .replace("<year>", &now.format("%Y").to_string())
+        .replace("<month>", &now.format("%m").to_string())
+        .replace("<day>", &now.format("%d").to_string())
+}
+
+pub fn get_dir_segments(rel_dir: &Path) -> Vec<String> {
+    rel_dir
+        .components()
+        .map(|comp| comp.as_os_str().to_string_lossy().into_owned())
+        .collect()
+}
+
+// Rendered remote_dir followed by the file's dir relative to its -d root, split into path segments.
+// Empty segments are dropped, so a leading "/" has to be checked on the template.
+pub fn get_remote_dirs(remote_dir: &str, rel_dir: &Path) -> Vec<String> {
+    let mut dirs: Vec<String> = render_date_vars(remote_dir)
+        .split('/')
+        .filter(|seg| !seg.is_empty())
+        .map(|seg| seg.to_string())
+        .collect();
+    dirs.extend(get_dir_segments(rel_dir));
+    dirs
+}
+
+// Remote paths always use "/", whatever the local OS.
+pub fn join_remote_path(absolute: bool, segments: &[String]) -> String {
+    let path = segments.join("/");
+    if absolute {
+        format!("/{}", path)
+    } else {
+        path
+    }
+}
+
+// Appends percent-encoded segments to a public url prefix.
+pub fn join_url_segments(prefix: &str, segments: &[String]) -> Result<String, Box<dyn Error>> {
+    let mut url = Url::parse(&render_date_vars(prefix))?;
+    url.path_segments_mut()
+        .map_err(|_| "invalid public url")?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url.to_string())
+}
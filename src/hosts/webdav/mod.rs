@@ -0,0 +1,5 @@
+#[allow(clippy::module_inception)]
+pub mod webdav;
+mod structs;
+
+pub use webdav::{delete, run};
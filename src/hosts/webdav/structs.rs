@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ShareMeta {
+    pub statuscode: u32,
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ShareData {
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+pub struct ShareOcs {
+    pub meta: ShareMeta,
+    // An empty array when the share wasn't created, so it's only read once meta says it was.
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+pub struct ShareResp {
+    pub ocs: ShareOcs,
+}
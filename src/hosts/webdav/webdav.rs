@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::error::Error;
+use reqwest::{Method, StatusCode};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use url::Url;
+use crate::client::UploaderClient;
+use crate::hosts::errors::{InvalidAPIKeyError, UnexpectedResponseError};
+use crate::hosts::structs::UploadResult;
+use crate::hosts::utils;
+use crate::hosts::webdav::structs::{ShareData, ShareResp};
+use crate::structs::UploadFile;
+
+const HOST: &str = "webdav";
+const SHARE_API_PATH: &str = "ocs/v2.php/apps/files_sharing/api/v1/shares";
+// Public link share.
+const SHARE_TYPE_LINK: &str = "3";
+
+fn make_headers(c: &UploaderClient, webdav_cfg: &HashMap<String, String>) -> Result<HeaderMap, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+
+    let token = utils::get_config_val(webdav_cfg, "token", false)?;
+    let username = utils::get_config_val(webdav_cfg, "username", false)?;
+
+    if !token.is_empty() {
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token))?);
+    } else if !username.is_empty() {
+        let password = utils::get_config_val(webdav_cfg, "password", false)?;
+        headers.insert(AUTHORIZATION, c.basic_auth_header(&username, &password)?);
+    }
+    Ok(headers)
+}
+
+fn push_segments(base: &Url, segments: &[String]) -> Result<Url, Box<dyn Error>> {
+    let mut url = base.clone();
+    url.path_segments_mut()
+        .map_err(|_| "invalid webdav url")?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url)
+}
+
+// Creates each missing collection along the path, remembering the ones already made this run.
+fn make_collections(c: &mut UploaderClient, base: &Url, dirs: &[String], headers: &HeaderMap, s: &mut HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    for i in 1..=dirs.len() {
+        let mut url = push_segments(base, &dirs[..i])?;
+        url.path_segments_mut()
+            .map_err(|_| "invalid webdav url")?
+            .push("");
+
+        let session_key = format!("mkcol:{}", url);
+        if !utils::get_session_val(s, HOST, &session_key).is_empty() {
+            continue;
+        }
+
+        let mkcol = Method::from_bytes(b"MKCOL")?;
+        if let Err(err) = c.do_request(mkcol, url.as_str(), Some(headers.clone()), None) {
+            // 405 means the collection already exists.
+            if utils::get_error_status(err.as_ref()) != Some(StatusCode::METHOD_NOT_ALLOWED) {
+                return Err(err)
+            }
+        }
+        utils::set_session_val(s, HOST, &session_key, "y");
+    }
+    Ok(())
+}
+
+// Splits a Nextcloud dav url into the server root and the file's path in the user's files.
+fn split_nextcloud_url(file_url: &Url) -> Result<(Url, String), Box<dyn Error>> {
+    let path = file_url.path();
+    let idx = path.find("/remote.php/")
+        .ok_or("webdav url isn't a Nextcloud/ownCloud one")?;
+
+    let dav_path = &path[idx + "/remote.php/".len()..];
+    let user_path = if let Some(rest) = dav_path.strip_prefix("dav/files/") {
+        rest.split_once('/').map(|(_, p)| p).unwrap_or("")
+    } else if let Some(rest) = dav_path.strip_prefix("webdav/") {
+        rest
+    } else {
+        return Err("webdav url isn't a Nextcloud/ownCloud files url".into())
+    };
+
+    let decoded = percent_encoding::percent_decode_str(user_path).decode_utf8()?;
+    let mut server = file_url.clone();
+    server.set_path(&format!("{}/", &path[..idx]));
+    server.set_query(None);
+    Ok((server, format!("/{}", decoded)))
+}
+
+fn create_share(c: &mut UploaderClient, file_url: &Url, headers: &HeaderMap) -> Result<String, Box<dyn Error>> {
+    let (server, share_path) = split_nextcloud_url(file_url)?;
+    let mut url = server.join(SHARE_API_PATH)?;
+    url.set_query(Some("format=json"));
+
+    let mut headers = headers.clone();
+    headers.insert("OCS-APIRequest", HeaderValue::from_static("true"));
+
+    let fields: Vec<(String, String)> = vec![
+        ("path".to_string(), share_path),
+        ("shareType".to_string(), SHARE_TYPE_LINK.to_string()),
+    ];
+
+    // A failed OCS status comes back as a matching 4xx, the message in the body says why.
+    let resp = c.do_form_post_unchecked(url.as_str(), Some(headers), &fields)?;
+    let status = resp.status();
+    parse_share_resp(status, &resp.text()?)
+}
+
+fn parse_share_resp(status: StatusCode, data: &str) -> Result<String, Box<dyn Error>> {
+    let json_obj: ShareResp = match serde_json::from_str(data) {
+        Ok(json_obj) => json_obj,
+        // Not an OCS reply at all, e.g. a proxy's error page.
+        Err(_) if !status.is_success() => return Err(format!("failed to create share: {}", status).into()),
+        Err(err) => return Err(err.into()),
+    };
+    if json_obj.ocs.meta.statuscode != 200 {
+        let message = json_obj.ocs.meta.message.unwrap_or_default();
+        return Err(format!("failed to create share: {}", message).into())
+    }
+
+    let share_data: ShareData = serde_json::from_value(json_obj.ocs.data.ok_or(UnexpectedResponseError)?)?;
+    Ok(share_data.url)
+}
+
+pub fn run(c: &mut UploaderClient, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    let webdav_cfg = utils::get_section_or_empty(cfg, HOST);
+    let base = Url::parse(&utils::get_config_val(&webdav_cfg, "url", true)?)?;
+    let headers = make_headers(c, &webdav_cfg)?;
+
+    let dirs = utils::get_dir_segments(&file.rel_dir);
+    make_collections(c, &base, &dirs, &headers, s)?;
+
+    let mut segments = dirs;
+    segments.push(file.name.clone());
+    let file_url = push_segments(&base, &segments)?;
+
+    if let Err(err) = c.put_upload(file_url.as_str(), &file.path, Some(headers.clone())) {
+        if let Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) = utils::get_error_status(err.as_ref()) {
+            return Err(InvalidAPIKeyError.into())
+        }
+        return Err(err)
+    }
+
+    let share = utils::get_config_val(&webdav_cfg, "nextcloud_share", false)?;
+    let mut result = if share == "y" {
+        UploadResult::new(create_share(c, &file_url, &headers)?)
+    } else {
+        UploadResult::new(file_url.to_string())
+    };
+
+    result.management.insert("dav_url".to_string(), file_url.to_string());
+    Ok(result)
+}
+
+pub fn delete(c: &mut UploaderClient, result: &UploadResult, cfg: &HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let dav_url = result.management.get("dav_url").ok_or("upload has no webdav url")?;
+
+    let webdav_cfg = utils::get_section_or_empty(cfg, HOST);
+    let headers = make_headers(c, &webdav_cfg)?;
+
+    c.do_request(Method::DELETE, dav_url, Some(headers), None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::test_server::{self, Response, TestServer};
+    use super::*;
+
+    fn share_server(share_status: u16, share_body: serde_json::Value) -> TestServer {
+        TestServer::new(move |req| match req.method.as_str() {
+            "POST" => Response::json(share_status, share_body.clone()),
+            _ => Response::new(201),
+        })
+    }
+
+    fn upload(server: &TestServer) -> Result<UploadResult, Box<dyn Error>> {
+        let url = format!("{}/remote.php/dav/files/alice/uploads", server.url);
+        let cfg = test_server::config(HOST, &[("url", &url), ("username", "alice"), ("password", "pw"), ("nextcloud_share", "y")]);
+        let file = test_server::upload_file("a.txt", "a.txt", b"hello");
+        run(&mut UploaderClient::new().unwrap(), &file, &cfg, &mut HashMap::new())
+    }
+
+    #[test]
+    fn share_link() {
+        let server = share_server(200, json!({
+            "ocs": {"meta": {"status": "ok", "statuscode": 200, "message": "OK"}, "data": {"id": "12", "url": "https://cloud.example.com/s/AbC123"}},
+        }));
+        let result = upload(&server).unwrap();
+        assert_eq!(result.url, "https://cloud.example.com/s/AbC123");
+
+        let requests = server.requests();
+        assert_eq!(server.paths(), [
+            "PUT /remote.php/dav/files/alice/uploads/a.txt",
+            "POST /ocs/v2.php/apps/files_sharing/api/v1/shares?format=json",
+        ]);
+        assert_eq!(requests[1].header("OCS-APIRequest"), Some("true"));
+        assert_eq!(requests[1].text(), "path=%2Fuploads%2Fa.txt&shareType=3");
+    }
+
+    #[test]
+    fn share_failure_message() {
+        let server = share_server(404, json!({
+            "ocs": {"meta": {"status": "failure", "statuscode": 404, "message": "Wrong path, file/folder does not exist"}, "data": []},
+        }));
+        let err = upload(&server).err().unwrap();
+        assert_eq!(err.to_string(), "failed to create share: Wrong path, file/folder does not exist");
+    }
+
+    #[test]
+    fn share_failure_without_ocs_reply() {
+        let server = TestServer::new(|req| match req.method.as_str() {
+            "POST" => Response::text(502, "<html>Bad Gateway</html>"),
+            _ => Response::new(201),
+        });
+        let err = upload(&server).err().unwrap();
+        assert_eq!(err.to_string(), "failed to create share: 502 Bad Gateway");
+    }
+}
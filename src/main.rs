@@ -1,31 +1,100 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use clap::Parser;
 
 mod utils;
 mod structs;
+mod actions;
 mod client;
+mod filters;
+mod history;
+mod hooks;
 mod hosts;
-
-use structs::Args;
+mod manifest;
+mod sorting;
+#[cfg(test)]
+mod test_server;
+mod watch;
+mod webhooks;
+
+use actions::LocalAction;
+use structs::{Args, BatchSummary, CollectionResult, Command, FileOverride, ScanOptions, UploadFile, UploadRecord};
 use crate::client::UploaderClient;
-use crate::hosts::enums::Host;
-use crate::hosts::errors::{SectionKeyMissingError, SectionValueEmptyError};
+use crate::filters::{FileFilter, GlobFilter};
+use crate::hooks::Hooks;
+use crate::hosts::enums::{Host, HostArg};
+use crate::hosts::structs::UploadResult;
+use crate::hosts::errors::{InvalidAPIKeyError, SectionKeyMissingError, SectionValueEmptyError};
+use crate::webhooks::Notifier;
+
+const TEMPLATE_MANAGEMENT_VARS: [&str; 10] = ["blob", "bucket", "cid", "container", "file_id", "file_name", "folder_id", "key", "name", "remote_path"];
 
 type RunFuncType = fn(
-    &mut UploaderClient, &PathBuf,
-    usize, &HashMap<String, HashMap<String, String>>,
+    &mut UploaderClient, &UploadFile,
+    &HashMap<String, HashMap<String, String>>,
     &mut HashMap<String, HashMap<String, String>>
-) -> Result<String, Box<dyn Error>>;
+) -> Result<UploadResult, Box<dyn Error>>;
 pub fn make_func_map() -> HashMap<Host, RunFuncType> {
     let mut m: HashMap<Host, RunFuncType> = HashMap::new();
 
+    m.insert(Host::Azblob, hosts::azblob::run);
+    m.insert(Host::B2, hosts::b2::run);
+    m.insert(Host::Catbox, hosts::catbox::run);
     m.insert(Host::Fileio, hosts::fileio::run);
+    m.insert(Host::Ftp, hosts::ftp::run);
+    m.insert(Host::Gcs, hosts::gcs::run);
     m.insert(Host::Gofile, hosts::gofile::run);
+    m.insert(Host::Ipfs, hosts::ipfs::run);
+    m.insert(Host::Litterbox, hosts::litterbox::run);
+    m.insert(Host::Nullpointer, hosts::nullpointer::run);
     m.insert(Host::Pixeldrain, hosts::pixeldrain::run);
+    m.insert(Host::S3, hosts::s3::run);
+    m.insert(Host::Sftp, hosts::sftp::run);
+    m.insert(Host::Transfersh, hosts::transfersh::run);
+    m.insert(Host::Webdav, hosts::webdav::run);
+    m
+}
+
+// Called once per host after all files, returns the collection link if the host made one.
+type BatchFuncType = fn(
+    &mut UploaderClient, &HashMap<String, HashMap<String, String>>,
+    &mut HashMap<String, HashMap<String, String>>
+) -> Result<Option<String>, Box<dyn Error>>;
+pub fn make_batch_func_map() -> HashMap<Host, BatchFuncType> {
+    let mut m: HashMap<Host, BatchFuncType> = HashMap::new();
+
+    m.insert(Host::Catbox, hosts::catbox::finish);
+    m.insert(Host::Ipfs, hosts::ipfs::finish);
+    m.insert(Host::Pixeldrain, hosts::pixeldrain::finish);
+    m.insert(Host::Transfersh, hosts::transfersh::finish);
+    m
+}
+
+type DeleteFuncType = fn(
+    &mut UploaderClient, &UploadResult,
+    &HashMap<String, HashMap<String, String>>
+) -> Result<(), Box<dyn Error>>;
+pub fn make_delete_func_map() -> HashMap<Host, DeleteFuncType> {
+    let mut m: HashMap<Host, DeleteFuncType> = HashMap::new();
+
+    m.insert(Host::Azblob, hosts::azblob::delete);
+    m.insert(Host::B2, hosts::b2::delete);
+    m.insert(Host::Catbox, hosts::catbox::delete);
+    m.insert(Host::Fileio, hosts::fileio::delete);
+    m.insert(Host::Ftp, hosts::ftp::delete);
+    m.insert(Host::Gcs, hosts::gcs::delete);
+    m.insert(Host::Gofile, hosts::gofile::delete);
+    m.insert(Host::Ipfs, hosts::ipfs::delete);
+    m.insert(Host::Nullpointer, hosts::nullpointer::delete);
+    m.insert(Host::Pixeldrain, hosts::pixeldrain::delete);
+    m.insert(Host::S3, hosts::s3::delete);
+    m.insert(Host::Sftp, hosts::sftp::delete);
+    m.insert(Host::Transfersh, hosts::transfersh::delete);
+    m.insert(Host::Webdav, hosts::webdav::delete);
     m
 }
 
@@ -38,6 +107,7 @@ fn template_file_setup(path: &PathBuf, wipe: bool) -> std::io::Result<()> {
 
     let f = OpenOptions::new()
         .create(true)
+        .truncate(false)
         .write(true)
         .open(path)?;
 
@@ -48,20 +118,49 @@ fn template_file_setup(path: &PathBuf, wipe: bool) -> std::io::Result<()> {
     Ok(())
 }
 
-fn parse_args() -> Result<Args, Box<dyn Error>> {
-    let mut args = Args::parse();
+fn prepare_args(mut args: Args, cfg: &HashMap<String, HashMap<String, String>>) -> Result<Args, Box<dyn Error>> {
+    if args.file_paths.is_empty() && args.directories.is_empty() && args.from_file.is_none() {
+        return Err("file path, directory and/or --from-file required".into())
+    }
 
-    if args.file_paths.len() == 0 && args.directories.len() == 0 {
-        return Err("file path and/or directory required".into())
+    let mut overrides: Vec<(PathBuf, FileOverride)> = Vec::new();
+    if let Some(from_file) = &args.from_file {
+        for (path, file_override) in manifest::read_manifest(from_file, args.null)? {
+            args.file_paths.push(path.clone());
+            overrides.push((path, file_override));
+        }
+    }
+
+    let named_hosts = args.hosts.iter().chain(overrides.iter().flat_map(|(_, o)| o.hosts.iter()));
+    for host in named_hosts {
+        if let HostArg::Custom(name) = host {
+            hosts::custom::check_defined(name, cfg)?;
+        }
     }
 
-    if args.directories.len() > 0 {
-        utils::process_dirs(&mut args)?;
+    if !args.directories.is_empty() {
+        let scan_opts = ScanOptions::new(&args, cfg);
+        let mut glob_filter = GlobFilter::new(&args.include, &args.exclude, cfg)?;
+        utils::process_dirs(&mut args, &scan_opts, &mut glob_filter)?;
+        args.directories = utils::normalise_dirs(&args.directories)?;
     }
-    let paths = utils::filter_paths(args.file_paths)?;
-    if paths.len() == 0 {
+    let mut file_filter = FileFilter::new(&args);
+    let mut paths = utils::filter_paths(std::mem::take(&mut args.file_paths), &mut file_filter)?;
+    // Watched directories are allowed to start out empty.
+    if paths.is_empty() && !matches!(args.command, Some(Command::Watch { .. })) {
         return Err("all paths were filtered".into())
     }
+    sorting::sort_paths(&mut paths, args.sort, args.reverse);
+
+    // The first line wins for duplicates, same as filter_paths keeping the first path.
+    for (path, file_override) in overrides {
+        if let Ok(norm_path) = utils::get_norm_path(&path) {
+            args.file_overrides.entry(norm_path).or_insert(file_override);
+        }
+    }
+    if let Some(p) = paths.iter().find(|p| args.get_file_hosts(p).is_empty()) {
+        return Err(format!("no hosts for {}, pass --hosts or give it hosts in --from-file", p.display()).into())
+    }
 
     args.file_paths = paths;
 
@@ -71,15 +170,20 @@ fn parse_args() -> Result<Args, Box<dyn Error>> {
             args.out_path = Some(abs_path);
         }
     }
+
+    if let Some(json_path) = &args.json_path {
+        if !json_path.is_absolute() {
+            let abs_path = utils::get_abs_path(json_path)?;
+            args.json_path = Some(abs_path);
+        }
+    }
     Ok(args)
 }
 
-// Too many args, clean up.
-fn generate_template(mut template: String, filename: &str, file_path: &PathBuf, file_url: &str, host: &str) -> Vec<u8> {
-    template = template.replace("<filename>", filename);
-    template = template.replace("<file_path>", &file_path.to_string_lossy());
-    template = template.replace("<url>", file_url);
-    template = template.replace("<host>", host);
+fn generate_template(mut template: String, vars: &[(&str, &str)]) -> Vec<u8> {
+    for (name, value) in vars {
+        template = template.replace(&format!("<{}>", name), value);
+    }
 
     let newline = if cfg!(windows) {
         "\r\n"
@@ -91,13 +195,12 @@ fn generate_template(mut template: String, filename: &str, file_path: &PathBuf,
     template.into_bytes()
 }
 
-fn write_template(template: String, out_path: &PathBuf, file_path: &PathBuf, filename: &str, url: &str, host: &str) -> Result<(), io::Error>  {
-    let data = generate_template(template, &filename, file_path, &url, &host);
+fn write_template(template: String, out_path: &Path, vars: &[(&str, &str)]) -> Result<(), io::Error>  {
+    let data = generate_template(template, vars);
     let canon_path = utils::normalise_path_case(out_path)?;
 
     let mut f = OpenOptions::new()
         .append(true)
-        .write(true)
         .open(canon_path)?;
 
     f.write_all(&data)?;
@@ -105,20 +208,254 @@ fn write_template(template: String, out_path: &PathBuf, file_path: &PathBuf, fil
     Ok(())
 }
 
+// Nested tables become their own sections, e.g. [custom.<name>.headers] is "custom.<name>.headers".
+fn flatten_config(name: &str, table: toml::Table, config: &mut HashMap<String, HashMap<String, String>>) -> Result<(), Box<dyn Error>> {
+    let mut section: HashMap<String, String> = HashMap::new();
+    for (key, value) in table {
+        let full_key = if name.is_empty() { key.clone() } else { format!("{}.{}", name, key) };
+        match value {
+            toml::Value::String(s) => {
+                section.insert(key, s);
+            },
+            toml::Value::Table(t) => flatten_config(&full_key, t, config)?,
+            _ => return Err(format!("config value \"{}\" must be a string", full_key).into()),
+        }
+    }
+
+    if name.is_empty() {
+        if let Some(key) = section.keys().next() {
+            return Err(format!("config value \"{}\" must be under a section", key).into())
+        }
+    } else {
+        config.insert(name.to_string(), section);
+    }
+    Ok(())
+}
+
 fn read_config() -> Result<HashMap<String, HashMap<String, String>>, Box<dyn Error>> {
     let exe_path = utils::get_exe_path()?;
     let config_path = exe_path.join("config.toml");
     let data = fs::read_to_string(config_path)?;
-    let config: HashMap<String, HashMap<String, String>> = toml::from_str(&data)?;
+    let mut config: HashMap<String, HashMap<String, String>> = HashMap::new();
+    flatten_config("", toml::from_str(&data)?, &mut config)?;
     Ok(config)
 }
 
+fn confirm(prompt: &str) -> io::Result<bool> {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn delete(args: &Args, targets: &[String], host: Option<&str>, key: Option<&str>, yes: bool) -> Result<(), Box<dyn Error>> {
+    let delete_func_map = make_delete_func_map();
+
+    let mut cfg = read_config()?;
+    utils::apply_config_overrides(args, &mut cfg);
+
+    let history_path = history::get_history_path()?;
+    let mut records = history::read_records(&history_path)?;
+    let mut deleted = vec![false; records.len()];
+
+    let mut uploader_client = UploaderClient::new()?;
+
+    for target in targets {
+        let matched: Vec<usize> = records.iter()
+            .enumerate()
+            .filter(|(i, record)| !deleted[*i] && host.is_none_or(|h| record.host == h) && record.matches(target, key))
+            .map(|(i, _)| i)
+            .collect();
+
+        if matched.is_empty() {
+            println!("No upload in history matches: {}", target);
+            continue;
+        }
+        if matched.len() > 1 && !yes {
+            for i in &matched {
+                println!("{} ({})", records[*i].result.url, records[*i].host);
+            }
+            if !confirm(&format!("{} uploads match {}, delete all of them?", matched.len(), target))? {
+                println!("Skipped: {}", target);
+                continue;
+            }
+        }
+
+        for i in matched {
+            let record = &records[i];
+            println!("{} ({})", record.result.url, record.host);
+            let delete_func = match HostArg::parse(&record.host) {
+                HostArg::Builtin(host) => delete_func_map.get(&host),
+                HostArg::Custom(_) => None,
+            };
+
+            let Some(delete_func) = delete_func else {
+                println!("Host doesn't support deletion.");
+                continue;
+            };
+
+            match delete_func(&mut uploader_client, &record.result, &cfg) {
+                Ok(()) => {
+                    println!("Deleted.");
+                    deleted[i] = true;
+                },
+                Err(err) => println!("Delete failed.\n{:?}", err),
+            }
+        }
+    }
+
+    let mut deleted_iter = deleted.iter();
+    records.retain(|_| !deleted_iter.next().unwrap_or(&false));
+    history::write_records(&history_path, &records)?;
+
+    Ok(())
+}
+
+fn make_upload_file(args: &Args, p: &PathBuf) -> Result<UploadFile, Box<dyn Error>> {
+    let name = match args.file_overrides.get(p).and_then(|o| o.name.clone()) {
+        Some(name) => name,
+        None => utils::get_fname_string_from_path(p)?,
+    };
+    let file = UploadFile {
+        path: p.clone(),
+        name,
+        size: utils::get_file_size(p)?,
+        rel_dir: utils::get_rel_dir(p, &args.directories),
+    };
+    Ok(file)
+}
+
+fn upload_to_host(c: &mut UploaderClient, func_map: &HashMap<Host, RunFuncType>, host: &HostArg, file: &UploadFile, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>) -> Result<UploadResult, Box<dyn Error>> {
+    match host {
+        HostArg::Builtin(builtin) => func_map[builtin](c, file, cfg, s),
+        HostArg::Custom(name) => hosts::custom::run(c, name, file, cfg),
+    }
+}
+
+// Config and credential problems fail every following upload to the host too.
+// Runs the host's batch step, which makes the collection link if there is one and resets what it kept for the batch.
+fn finish_batch(args: &Args, c: &mut UploaderClient, batch_func_map: &HashMap<Host, BatchFuncType>, host: &HostArg, cfg: &HashMap<String, HashMap<String, String>>, s: &mut HashMap<String, HashMap<String, String>>, summary: &mut BatchSummary) -> Result<(), Box<dyn Error>> {
+    let HostArg::Builtin(builtin) = host else {
+        return Ok(())
+    };
+    let Some(batch_func) = batch_func_map.get(builtin) else {
+        return Ok(())
+    };
+
+    let collection_url = match batch_func(c, cfg, s) {
+        Ok(Some(url)) => url,
+        Ok(None) => return Ok(()),
+        Err(err) => {
+            println!("Failed to create collection.\n{:?}", err);
+            return Ok(())
+        },
+    };
+
+    println!("Collection:\n{}", collection_url);
+    summary.collections.push(CollectionResult {
+        host: host.to_string(),
+        url: collection_url.clone(),
+    });
+    if let Some(out_path) = &args.out_path {
+        let host_str = host.to_string();
+        let vars = [
+            ("url", collection_url.as_str()),
+            ("host", &host_str),
+        ];
+        write_template(args.collection_template.clone(), out_path, &vars)?;
+    }
+    Ok(())
+}
+
+fn is_fatal_error(err: &(dyn Error + 'static)) -> bool {
+    err.downcast_ref::<SectionKeyMissingError>().is_some() ||
+        err.downcast_ref::<SectionValueEmptyError>().is_some() ||
+        err.downcast_ref::<InvalidAPIKeyError>().is_some()
+}
+
+// Prints the links and writes them to the output template, JSON output and history.
+fn write_outputs(args: &Args, host: &HostArg, file: UploadFile, result: UploadResult, history_path: &PathBuf) -> Result<UploadRecord, Box<dyn Error>> {
+    println!("{}", result.url);
+    for mirror in &result.mirrors {
+        println!("{}", mirror);
+    }
+    if let Some(expires) = &result.expires {
+        println!("Expires: {}", expires);
+    }
+
+    if let Some(out_path) = &args.out_path {
+        let host_str = host.to_string();
+        let file_path_str = file.path.to_string_lossy();
+        let expires = result.expires.clone().unwrap_or_default();
+        let mirrors = result.mirrors.join(" ");
+        let mut vars = vec![
+            ("expires", expires.as_str()),
+            ("filename", &file.name),
+            ("file_path", &file_path_str),
+            ("mirrors", &mirrors),
+            ("url", &result.url),
+            ("host", &host_str),
+        ];
+        // Host specific values like a gofile file_id or IPFS cid, after the common vars so they can't shadow them.
+        // Tokens and delete urls are left out, link lists tend to get shared.
+        vars.extend(result.management.iter()
+            .filter(|(k, _)| TEMPLATE_MANAGEMENT_VARS.contains(&k.as_str()))
+            .map(|(k, v)| (k.as_str(), v.as_str())));
+        write_template(args.template.clone(), out_path, &vars)?;
+    }
+
+    let uploaded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)?
+        .as_secs();
+    let record = UploadRecord {
+        host: host.to_string(),
+        filename: file.name,
+        file_path: file.path,
+        uploaded_at,
+        result,
+    };
+
+    if let Some(json_path) = &args.json_path {
+        history::append_record(json_path, &record)?;
+    }
+
+    if let Err(err) = history::append_record(history_path, &record) {
+        println!("Failed to write to history.\n{:?}", err);
+    }
+    Ok(record)
+}
+
+// --on-success or --on-failure, once a file has been to all of its hosts.
+fn apply_local_action(args: &Args, p: &PathBuf, failed: bool) {
+    let action = if failed { &args.on_failure } else { &args.on_success };
+    let Some(action) = action else {
+        return
+    };
+
+    let result = make_upload_file(args, p).and_then(|file| actions::apply(action, &file));
+    if let Err(err) = result {
+        println!("Local file action failed for {}.\n{:?}", p.display(), err);
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let mut sessions_map: HashMap<String, HashMap<String, String>> = HashMap::new();
     let func_map = make_func_map();
+    let batch_func_map = make_batch_func_map();
 
-    let args = parse_args()?;
-    let file_total = args.file_paths.len();
+    let mut args = Args::parse();
+    if let Some(Command::Delete { targets, host, key, yes }) = &args.command {
+        return delete(&args, targets, host.as_deref(), key.as_deref(), *yes)
+    }
+    if let Some(Command::Watch { directories, hosts, .. }) = &args.command {
+        args.directories = directories.clone();
+        args.hosts = hosts.clone();
+    }
+
+    let mut cfg = read_config()?;
+    let args = prepare_args(args, &cfg)?;
 
     let mut uploader_client = UploaderClient::new()?;
 
@@ -126,41 +463,149 @@ fn main() -> Result<(), Box<dyn Error>> {
         template_file_setup(out_path, args.wipe)?;
     }
 
-    let cfg = read_config()?;
+    if let Some(json_path) = &args.json_path {
+        template_file_setup(json_path, args.wipe)?;
+    }
+
+    utils::apply_config_overrides(&args, &mut cfg);
+
+    let history_path = history::get_history_path()?;
+    let hooks = Hooks::new(&cfg);
+    let notifier = Notifier::new(&cfg)?;
 
-    let host_total =  args.hosts.len();
-    'outer: for (mut host_num, host) in args.hosts.iter().enumerate() {
+    if let Some(Command::Watch { .. }) = &args.command {
+        return watch::run(&args, &cfg, &mut uploader_client, &mut sessions_map, &history_path, &hooks, &notifier)
+    }
+
+    // Local actions wait for a file's last host, so a file is only deleted once every host has it.
+    let mut hosts_left: HashMap<&PathBuf, usize> = args.file_paths.iter()
+        .map(|p| (p, args.get_file_hosts(p).len()))
+        .collect();
+    let mut failed: HashSet<&PathBuf> = HashSet::new();
+    let mut skipped: HashSet<&PathBuf> = HashSet::new();
+    let mut summary = BatchSummary::default();
+
+    let all_hosts = args.get_all_hosts();
+    let host_total =  all_hosts.len();
+    'outer: for (mut host_num, host) in all_hosts.iter().enumerate() {
         host_num += 1;
         println!("Host {} of {}:", host_num, host_total);
-        println!("{}", host.to_string());
+        println!("{}", host);
 
-        for (mut file_num, p) in args.file_paths.iter().enumerate() {
+        let host_files: Vec<&PathBuf> = args.file_paths.iter()
+            .filter(|p| args.get_file_hosts(p).contains(host))
+            .collect();
+        let file_total = host_files.len();
+
+        for (mut file_num, p) in host_files.into_iter().enumerate() {
             file_num += 1;
 
             println!("File {} of {}:", file_num, file_total);
-            let filename = utils::get_fname_string_from_path(p)?;
-            let file_size = utils::get_file_size(&p)?;
+            let mut file = make_upload_file(&args, p)?;
 
             utils::print_path_no_prefix(p);
-            let url = match func_map[&host](&mut uploader_client, p, file_size, &cfg, &mut sessions_map) {
-                Ok(url) => url,
-                Err(err) => {
-                    println!("Upload failed.\n{:?}", err);
-                    if err.downcast_ref::<SectionKeyMissingError>().is_some() ||
-                        err.downcast_ref::<SectionValueEmptyError>().is_some() {
-                        break 'outer
-                    }
-                    continue;
-                },
-            };
+            if hooks.pre_upload(&file, host) {
+                // The hook may have changed the file.
+                file.size = utils::get_file_size(p)?;
+                match upload_to_host(&mut uploader_client, &func_map, host, &file, &cfg, &mut sessions_map) {
+                    Ok(result) => {
+                        if let Some(LocalAction::Sidecar) = args.on_success {
+                            if let Err(err) = actions::write_sidecar(&file, host, args.get_file_hosts(p).len(), &result.url) {
+                                println!("Failed to write sidecar.\n{:?}", err);
+                            }
+                        }
+                        let record = write_outputs(&args, host, file, result, &history_path)?;
+                        hooks.post_upload(&record);
+                        summary.add_uploaded(&record);
+                    },
+                    Err(err) => {
+                        println!("Upload failed.\n{:?}", err);
+                        hooks.on_failure(&file, host, err.as_ref());
+                        summary.add_failed(&file, host, err.as_ref());
+                        failed.insert(p);
+                        if is_fatal_error(err.as_ref()) {
+                            break 'outer
+                        }
+                    },
+                };
+            } else {
+                println!("Skipped by pre_upload hook.");
+                summary.add_skipped(&file, host);
+                skipped.insert(p);
+            }
 
-            println!("{}", url);
-            if let Some(out_path) = &args.out_path {
-                write_template(args.template.clone(), out_path, p, &filename, &url, &host.to_string())?;
+            if let Some(left) = hosts_left.get_mut(p) {
+                *left -= 1;
+                // Files skipped for a host are neither done nor failed.
+                if *left == 0 && (failed.contains(p) || !skipped.contains(p)) {
+                    apply_local_action(&args, p, failed.contains(p));
+                }
             }
+        }
 
+        finish_batch(&args, &mut uploader_client, &batch_func_map, host, &cfg, &mut sessions_map, &mut summary)?;
+    }
+
+    // Files a fatal error stopped before their last host.
+    for p in &args.file_paths {
+        if failed.contains(p) && hosts_left.get(p).is_some_and(|left| *left > 0) {
+            apply_local_action(&args, p, true);
         }
     }
 
+    hooks.on_batch_done(&summary);
+    notifier.send(&uploader_client, &summary);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_server::{self, Response, TestServer};
+    use super::*;
+
+    #[test]
+    fn from_file_names_reach_multipart_uploads() {
+        let dir = test_server::temp_dir();
+        let file_path = dir.join("on-disk.bin");
+        fs::write(&file_path, b"hello").unwrap();
+        let manifest_path = dir.join("list.txt");
+        fs::write(&manifest_path, format!("{}\t\trenamed.txt\n", file_path.display())).unwrap();
+
+        let args = Args::try_parse_from(["uploader-rs", "--hosts", "catbox", "--from-file", &manifest_path.to_string_lossy()]).unwrap();
+        let args = prepare_args(args, &HashMap::new()).unwrap();
+        let file = make_upload_file(&args, &args.file_paths[0]).unwrap();
+        assert_eq!(file.name, "renamed.txt");
+
+        let server = TestServer::new(|_| Response::new(200));
+        UploaderClient::new().unwrap()
+            .multipart_upload(&server.url, "fileToUpload".to_string(), &file, None, None, None)
+            .unwrap();
+        assert!(server.requests()[0].text().contains("name=\"fileToUpload\"; filename=\"renamed.txt\""));
+    }
+
+    #[test]
+    fn finishing_a_batch_resets_host_state() {
+        let args = Args::try_parse_from(["uploader-rs", "--hosts", "transfersh"]).unwrap();
+        let host = HostArg::Builtin(Host::Transfersh);
+        let batch_func_map = make_batch_func_map();
+        let mut c = UploaderClient::new().unwrap();
+        let mut summary = BatchSummary::default();
+
+        let cfg = test_server::config("transfersh", &[("base_url", "https://transfer.example"), ("bundle", "zip")]);
+        let mut s = test_server::config("transfersh", &[("bundle_entries", "aaa/a.txt,bbb/b.txt")]);
+        finish_batch(&args, &mut c, &batch_func_map, &host, &cfg, &mut s, &mut summary).unwrap();
+        assert_eq!(summary.collections[0].url, "https://transfer.example/(aaa/a.txt,bbb/b.txt).zip");
+
+        // The next round only bundles its own files.
+        s = test_server::config("transfersh", &[("bundle_entries", "ccc/c.txt")]);
+        finish_batch(&args, &mut c, &batch_func_map, &host, &cfg, &mut s, &mut summary).unwrap();
+        assert_eq!(summary.collections[1].url, "https://transfer.example/(ccc/c.txt).zip");
+
+        // Without bundles the entries are still dropped.
+        let cfg = test_server::config("transfersh", &[]);
+        s = test_server::config("transfersh", &[("bundle_entries", "ddd/d.txt")]);
+        finish_batch(&args, &mut c, &batch_func_map, &host, &cfg, &mut s, &mut summary).unwrap();
+        assert_eq!(summary.collections.len(), 2);
+        assert_eq!(s["transfersh"]["bundle_entries"], "");
+    }
+}
@@ -0,0 +1,154 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::hosts::enums::HostArg;
+use crate::structs::FileOverride;
+
+#[derive(Deserialize)]
+struct JsonEntry {
+    path: PathBuf,
+    #[serde(default)]
+    hosts: Vec<String>,
+    name: Option<String>,
+}
+
+fn read_input(path: &Path) -> io::Result<Vec<u8>> {
+    if path.as_os_str() == "-" {
+        let mut data: Vec<u8> = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        fs::read(path)
+    }
+}
+
+fn make_override(hosts: &[String], name: Option<String>) -> Result<FileOverride, Box<dyn Error>> {
+    let hosts = hosts.iter()
+        .map(|h| h.trim())
+        .filter(|h| !h.is_empty())
+        .map(HostArg::from_name)
+        .collect::<Result<Vec<HostArg>, String>>()?;
+
+    let name = name.filter(|n| !n.is_empty());
+    if let Some(name) = &name {
+        if name.contains(['/', '\\']) {
+            return Err(format!("name can't contain path separators: {}", name).into())
+        }
+    }
+    Ok(FileOverride { hosts, name })
+}
+
+// A plain path, a JSON object, or tab separated path, comma separated hosts and name. Empty columns keep the defaults.
+// Any line starting with { is taken as JSON, so a path that starts with one has to be written as ./{...} instead.
+fn parse_line(line: &str) -> Result<(PathBuf, FileOverride), Box<dyn Error>> {
+    if line.starts_with('{') {
+        let entry: JsonEntry = serde_json::from_str(line)?;
+        return Ok((entry.path, make_override(&entry.hosts, entry.name)?))
+    }
+
+    let mut columns = line.split('\t');
+    let path = PathBuf::from(columns.next().unwrap_or_default());
+    let hosts: Vec<String> = columns.next()
+        .map(|c| c.split(',').map(|h| h.to_string()).collect())
+        .unwrap_or_default();
+    let name = columns.next().map(|c| c.to_string());
+    if columns.next().is_some() {
+        return Err("too many columns, expected path, hosts and name".into())
+    }
+    Ok((path, make_override(&hosts, name)?))
+}
+
+// Paths to upload from a file or stdin (-). NUL separated input is taken as plain paths, since those may contain tabs.
+pub fn read_manifest(path: &Path, null_separated: bool) -> Result<Vec<(PathBuf, FileOverride)>, Box<dyn Error>> {
+    parse_manifest(read_input(path)?, path, null_separated)
+}
+
+fn parse_manifest(data: Vec<u8>, path: &Path, null_separated: bool) -> Result<Vec<(PathBuf, FileOverride)>, Box<dyn Error>> {
+    let mut entries: Vec<(PathBuf, FileOverride)> = Vec::new();
+
+    if null_separated {
+        for record in data.split(|b| *b == b'\0').filter(|r| !r.is_empty()) {
+            let record = String::from_utf8(record.to_vec())?;
+            entries.push((PathBuf::from(record), FileOverride::default()));
+        }
+        return Ok(entries)
+    }
+
+    let text = String::from_utf8(data)?;
+    for (line_num, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = parse_line(line)
+            .map_err(|e| format!("{}:{}: {}", path.display(), line_num + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hosts::enums::Host;
+    use super::*;
+
+    fn parse(data: &str, null_separated: bool) -> Result<Vec<(PathBuf, FileOverride)>, Box<dyn Error>> {
+        parse_manifest(data.as_bytes().to_vec(), Path::new("list.txt"), null_separated)
+    }
+
+    #[test]
+    fn tsv_lines() {
+        let entries = parse("a.zip\nbuild/app.zip\ts3, gofile\tapp-1.2.0.zip\r\n\n  \nnotes.txt\t\trelease-notes.txt\nc.txt\tmyhost\t\n", false).unwrap();
+        assert_eq!(entries.len(), 4);
+
+        assert_eq!(entries[0].0, PathBuf::from("a.zip"));
+        assert!(entries[0].1.hosts.is_empty() && entries[0].1.name.is_none());
+
+        assert_eq!(entries[1].0, PathBuf::from("build/app.zip"));
+        assert_eq!(entries[1].1.hosts, [HostArg::Builtin(Host::S3), HostArg::Builtin(Host::Gofile)]);
+        assert_eq!(entries[1].1.name.as_deref(), Some("app-1.2.0.zip"));
+
+        assert!(entries[2].1.hosts.is_empty());
+        assert_eq!(entries[2].1.name.as_deref(), Some("release-notes.txt"));
+
+        assert_eq!(entries[3].1.hosts, [HostArg::Custom("myhost".to_string())]);
+        assert!(entries[3].1.name.is_none());
+    }
+
+    #[test]
+    fn jsonl_lines() {
+        let entries = parse("{\"path\": \"build/app.zip\", \"hosts\": [\"s3\", \"gofile\"], \"name\": \"app.zip\"}\n{\"path\": \"a\\tb.txt\"}\n", false).unwrap();
+        assert_eq!(entries[0].0, PathBuf::from("build/app.zip"));
+        assert_eq!(entries[0].1.hosts, [HostArg::Builtin(Host::S3), HostArg::Builtin(Host::Gofile)]);
+        assert_eq!(entries[0].1.name.as_deref(), Some("app.zip"));
+
+        assert_eq!(entries[1].0, PathBuf::from("a\tb.txt"));
+        assert!(entries[1].1.hosts.is_empty() && entries[1].1.name.is_none());
+    }
+
+    #[test]
+    fn braces_need_a_prefix() {
+        assert!(parse("{weird}.txt\n", false).is_err());
+        let entries = parse("./{weird}.txt\n", false).unwrap();
+        assert_eq!(entries[0].0, PathBuf::from("./{weird}.txt"));
+    }
+
+    #[test]
+    fn nul_separated() {
+        let entries = parse("a b.txt\0tab\there.txt\0{x}.txt\0\0line\nbreak.txt\0", true).unwrap();
+        let paths: Vec<PathBuf> = entries.iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(paths, ["a b.txt", "tab\there.txt", "{x}.txt", "line\nbreak.txt"].map(PathBuf::from));
+        assert!(entries.iter().all(|(_, o)| o.hosts.is_empty() && o.name.is_none()));
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let err = parse("ok.txt\na\tb\tc\td\n", false).err().unwrap();
+        assert_eq!(err.to_string(), "list.txt:2: too many columns, expected path, hosts and name");
+        assert!(parse("a.txt\t\tsub/name.txt\n", false).is_err());
+        assert!(parse("a.txt\tbad.host\n", false).is_err());
+        assert!(parse("{\"hosts\": [\"s3\"]}\n", false).is_err());
+    }
+}
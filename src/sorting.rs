@@ -0,0 +1,144 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::structs::SortOrder;
+
+// Digit runs compare as numbers, so ep2 comes before ep10. Letters ignore case, like file managers do.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let (a_c, b_c) = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_c), Some(b_c)) => (*a_c, *b_c),
+        };
+
+        if a_c.is_ascii_digit() && b_c.is_ascii_digit() {
+            let a_num = take_digits(&mut a_chars);
+            let b_num = take_digits(&mut b_chars);
+            let a_trimmed = a_num.trim_start_matches('0');
+            let b_trimmed = b_num.trim_start_matches('0');
+
+            let ord = a_trimmed.len().cmp(&b_trimmed.len())
+                .then_with(|| a_trimmed.cmp(b_trimmed))
+                // Fewer leading zeros first, so 1 comes before 01.
+                .then_with(|| a_num.len().cmp(&b_num.len()));
+            if ord != Ordering::Equal {
+                return ord
+            }
+            continue;
+        }
+
+        let ord = a_c.to_lowercase().cmp(b_c.to_lowercase());
+        if ord != Ordering::Equal {
+            return ord
+        }
+        a_chars.next();
+        b_chars.next();
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits
+}
+
+// Component by component, so files stay grouped under their directories.
+fn natural_path_cmp(a: &Path, b: &Path) -> Ordering {
+    let a_comps = a.components().map(|c| c.as_os_str().to_string_lossy());
+    let b_comps = b.components().map(|c| c.as_os_str().to_string_lossy());
+
+    a_comps.zip(b_comps)
+        .map(|(a_comp, b_comp)| natural_cmp(&a_comp, &b_comp))
+        .find(|ord| *ord != Ordering::Equal)
+        .unwrap_or_else(|| a.components().count().cmp(&b.components().count()))
+        .then_with(|| a.cmp(b))
+}
+
+fn get_mtime(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn get_size(path: &Path) -> u64 {
+    fs::metadata(path)
+        .map(|meta| meta.len())
+        .unwrap_or(0)
+}
+
+// Each file's metadata is only read once. Ties are broken by natural path order, so the result is the same on every run.
+fn sort_by_metadata<K: Ord>(paths: &mut Vec<PathBuf>, get_key: fn(&Path) -> K) {
+    let mut keyed: Vec<(K, PathBuf)> = paths.drain(..)
+        .map(|p| (get_key(&p), p))
+        .collect();
+    keyed.sort_by(|(a_key, a), (b_key, b)| a_key.cmp(b_key).then_with(|| natural_path_cmp(a, b)));
+    paths.extend(keyed.into_iter().map(|(_, p)| p));
+}
+
+pub fn sort_paths(paths: &mut Vec<PathBuf>, order: SortOrder, reverse: bool) {
+    match order {
+        SortOrder::Name => paths.sort(),
+        SortOrder::Natural => paths.sort_by(|a, b| natural_path_cmp(a, b)),
+        SortOrder::Size => sort_by_metadata(paths, get_size),
+        SortOrder::Mtime => sort_by_metadata(paths, get_mtime),
+    }
+
+    if reverse {
+        paths.reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(paths: &[&str]) -> Vec<String> {
+        let mut paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        sort_paths(&mut paths, SortOrder::Natural, false);
+        paths.iter().map(|p| p.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("ep2", "ep10"), Ordering::Less);
+        assert_eq!(natural_cmp("ep10", "ep9"), Ordering::Greater);
+        assert_eq!(natural_cmp("v1.2.10", "v1.2.9"), Ordering::Greater);
+        assert_eq!(natural_cmp("ep", "ep1"), Ordering::Less);
+        assert_eq!(natural_cmp("99999999999999999999999", "100000000000000000000000"), Ordering::Less);
+    }
+
+    #[test]
+    fn leading_zeros() {
+        assert_eq!(natural_cmp("ep02", "ep10"), Ordering::Less);
+        assert_eq!(natural_cmp("ep007", "ep6"), Ordering::Greater);
+        assert_eq!(natural_cmp("ep1", "ep01"), Ordering::Less);
+        assert_eq!(natural_cmp("ep01", "ep001"), Ordering::Less);
+        assert_eq!(natural_cmp("ep0", "ep00"), Ordering::Less);
+    }
+
+    #[test]
+    fn case_folding() {
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("Banana", "cherry"), Ordering::Less);
+        assert_eq!(natural_cmp("README", "readme"), Ordering::Equal);
+        assert_eq!(natural_cmp("Ärger", "ärger"), Ordering::Equal);
+    }
+
+    #[test]
+    fn mixed_paths() {
+        assert_eq!(
+            sorted(&["show/ep10.mkv", "Show 2/ep1.mkv", "show/ep2.mkv", "show/extras/ep1.mkv", "show/Ep1.mkv", "show-b.mkv"]),
+            ["show/Ep1.mkv", "show/ep2.mkv", "show/ep10.mkv", "show/extras/ep1.mkv", "Show 2/ep1.mkv", "show-b.mkv"],
+        );
+        // Names that only differ in case still get a stable order.
+        assert_eq!(sorted(&["b.txt", "B.txt"]), ["B.txt", "b.txt"]);
+    }
+}
@@ -1,28 +1,331 @@
+use std::collections::HashMap;
+use std::error::Error;
 use std::path::PathBuf;
-use clap::Parser;
-use crate::hosts::enums::Host;
+use std::time::SystemTime;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use crate::actions::LocalAction;
+use crate::filters;
+use crate::hosts::enums::{HostArg, HostArgParser};
+use crate::hosts::sizes::Size;
+use crate::hosts::structs::UploadResult;
 
 #[derive(Parser)]
-#[command(name = "uploader.rs")]
+#[command(name = "uploader.rs", subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[clap(short, long, default_value = "<url><newline>", help = "Output template for collection links (catbox albums, IPFS directories, pixeldrain lists, transfer.sh bundles). Vars: host, newline, url.")]
+    pub collection_template: String,
+
     #[clap(short, long, num_args = 1..)]
     pub directories: Vec<PathBuf>,
 
+    #[clap(long, num_args = 1.., global = true, help = "Skip files under -d matching these globs, relative to the directory. Patterns without a slash match any path component.")]
+    pub exclude: Vec<String>,
+
     #[clap(short, long, num_args = 1..)]
     pub file_paths: Vec<PathBuf>,
 
-    #[clap(long, required = true, num_args = 1..)]
-    pub hosts: Vec<Host>,
+    #[clap(long, global = true, help = "file.io API key. Overrides config.")]
+    pub fileio_api_key: Option<String>,
+
+    #[clap(long, global = true, help = "Delete from file.io after the last download. Overrides config.")]
+    pub fileio_auto_delete: Option<bool>,
+
+    #[clap(long, global = true, help = "file.io expiry, e.g. 1d, 2w or an ISO 8601 date. Overrides config.")]
+    pub fileio_expires: Option<String>,
+
+    #[clap(long, global = true, help = "file.io download limit. Overrides config.")]
+    pub fileio_max_downloads: Option<u32>,
+
+    #[clap(long, global = true, help = "Follow symlinked directories under -d, symlinked files are always taken. Loops are detected and skipped.")]
+    pub follow_symlinks: bool,
+
+    #[clap(long, help = "Read paths to upload from this file, or - for stdin. One per line, optionally with per-file hosts and names as TSV or JSON Lines.")]
+    pub from_file: Option<PathBuf>,
+
+    #[clap(long, overrides_with = "no_hidden", global = true, help = "Include dotfiles and dot directories under -d, the default. Overrides config.")]
+    pub hidden: bool,
+
+    #[clap(long, required_unless_present = "from_file", num_args = 1.., value_parser = HostArgParser, help = "Hosts to upload to, built-in or defined under [custom.<name>] in the config.")]
+    pub hosts: Vec<HostArg>,
+
+    #[clap(long, overrides_with = "no_ignore_files", global = true, help = "Skip files under -d listed in .gitignore, .ignore and .uploaderignore files, like ripgrep. Overrides config.")]
+    pub ignore_files: bool,
+
+    #[clap(long, num_args = 1.., global = true, help = "Only take files under -d matching one of these globs, relative to the directory. Patterns without a slash match the file name.")]
+    pub include: Vec<String>,
+
+    #[clap(short, long, global = true, help = "JSON Lines output path, one record per upload.")]
+    pub json_path: Option<PathBuf>,
+
+    #[clap(long, value_parser = ["1h", "12h", "24h", "72h"], global = true, help = "litterbox expiry. Overrides config.")]
+    pub litterbox_time: Option<String>,
 
-    #[clap(short, long, help = "Output template path.")]
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..), global = true, help = "How many levels deep to scan -d directories, 1 takes only their own files. Implies -r.")]
+    pub max_depth: Option<u32>,
+
+    #[clap(long, value_parser = Size::custom_from_str, global = true, help = "Skip files larger than this, e.g. 500MB or 2GiB.")]
+    pub max_size: Option<Size>,
+
+    #[clap(long, num_args = 1.., global = true, help = "Only take files of these types, guessed from the extension, e.g. image/* or video/mp4.")]
+    pub mime: Vec<String>,
+
+    #[clap(long, value_parser = Size::custom_from_str, global = true, help = "Skip files smaller than this, e.g. 100KB.")]
+    pub min_size: Option<Size>,
+
+    #[clap(long, value_parser = filters::parse_time_arg, global = true, help = "Only take files modified within this long, e.g. 12h or 7d, or since a date like 2024-10-01.")]
+    pub newer_than: Option<SystemTime>,
+
+    #[clap(long, overrides_with = "hidden", global = true, help = "Skip dotfiles and dot directories under -d. Overrides config.")]
+    pub no_hidden: bool,
+
+    #[clap(long, overrides_with = "ignore_files", global = true, help = "Don't read ignore files under -d. Overrides config.")]
+    pub no_ignore_files: bool,
+
+    #[clap(short = '0', long, requires = "from_file", help = "--from-file paths are separated by NUL instead of newlines, e.g. from find -print0.")]
+    pub null: bool,
+
+    #[clap(long, value_parser = filters::parse_time_arg, global = true, help = "Only take files modified at least this long ago, or before a date.")]
+    pub older_than: Option<SystemTime>,
+
+    #[clap(long, value_parser = LocalAction::parse_failure, global = true, help = "What to do with a file when an upload fails, move:<dir>. Runs once all of its hosts were tried.")]
+    pub on_failure: Option<LocalAction>,
+
+    #[clap(long, value_parser = LocalAction::parse, global = true, help = "What to do with a file once every host has it, move:<dir> or delete, or sidecar to write a .url file next to it.")]
+    pub on_success: Option<LocalAction>,
+
+    #[clap(short, long, global = true, help = "Output template path.")]
     pub out_path: Option<PathBuf>,
 
-    #[clap(short, long, help = "Include subdirectories.")]
+    #[clap(short, long, global = true, help = "Include subdirectories.")]
     pub recursive: bool,
 
-    #[clap(short, long, default_value = "<url><newline>", help = "Output template. Vars: filename, file_path, host, newline, url.")]
+    #[clap(long, help = "Reverse the upload order.")]
+    pub reverse: bool,
+
+    #[clap(long, value_enum, default_value_t = SortOrder::Natural, help = "Upload order. natural is by path with numbers compared by value, so ep2 comes before ep10.")]
+    pub sort: SortOrder,
+
+    #[clap(short, long, default_value = "<url><newline>", global = true, help = "Output template. Vars: expires, filename, file_path, host, mirrors, newline, url, plus the host's non-secret management values, e.g. cid or file_id.")]
     pub template: String,
 
-    #[clap(short, long, help = "Wipe output template on startup.")]
+    #[clap(short, long, global = true, help = "Wipe output template on startup.")]
     pub wipe: bool,
-}
\ No newline at end of file
+
+    // Keyed by normalised path, filled from --from-file.
+    #[clap(skip)]
+    pub file_overrides: HashMap<PathBuf, FileOverride>,
+}
+
+impl Args {
+    // The hosts a file goes to, its --from-file hosts if it has any.
+    pub fn get_file_hosts(&self, path: &PathBuf) -> &[HostArg] {
+        match self.file_overrides.get(path) {
+            Some(file_override) if !file_override.hosts.is_empty() => &file_override.hosts,
+            _ => &self.hosts,
+        }
+    }
+
+    // --hosts first, then any extra ones from --from-file in the order they appear. Hosts left without files are dropped.
+    pub fn get_all_hosts(&self) -> Vec<HostArg> {
+        let mut all_hosts: Vec<HostArg> = self.hosts.clone();
+        for path in &self.file_paths {
+            for host in self.get_file_hosts(path) {
+                if !all_hosts.contains(host) {
+                    all_hosts.push(host.clone());
+                }
+            }
+        }
+        all_hosts.retain(|host| self.file_paths.iter().any(|p| self.get_file_hosts(p).contains(host)));
+        all_hosts
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct FileOverride {
+    pub hosts: Vec<HostArg>,
+    pub name: Option<String>,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum SortOrder {
+    Name,
+    Natural,
+    Size,
+    Mtime,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Delete uploads recorded in the history, by URL or host ID.")]
+    Delete {
+        #[clap(required = true, num_args = 1..)]
+        targets: Vec<String>,
+
+        #[clap(long, help = "Only delete uploads to this host.")]
+        host: Option<String>,
+
+        #[clap(long, help = "Match the targets against this management value instead of the URL or host ID, e.g. bucket or folder_id.")]
+        key: Option<String>,
+
+        #[clap(short, long, help = "Don't ask before deleting when a target matches more than one upload.")]
+        yes: bool,
+    },
+    #[command(about = "Watch directories and upload files as they appear.")]
+    Watch {
+        #[clap(short, long, required = true, num_args = 1..)]
+        directories: Vec<PathBuf>,
+
+        #[clap(long, required = true, num_args = 1.., value_parser = HostArgParser, help = "Hosts to upload to, built-in or defined under [custom.<name>] in the config.")]
+        hosts: Vec<HostArg>,
+
+        #[clap(long, help = "Journal of uploaded files, so they aren't uploaded again after a restart. Defaults to watch_journal.jsonl next to the executable.")]
+        journal: Option<PathBuf>,
+
+        #[clap(long, default_value_t = 5, help = "How many times a failed upload is retried, waiting longer each time.")]
+        retries: u32,
+
+        #[clap(long, default_value_t = 5, help = "Seconds a file's size has to stay the same before it's uploaded. Files closed after writing are taken right away.")]
+        settle: u64,
+    },
+}
+
+// How -d directories are walked, from the args with [scan] config values as defaults.
+pub struct ScanOptions {
+    // None scans all the way down.
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    pub hidden: bool,
+    pub ignore_files: bool,
+}
+
+fn resolve_flag(on: bool, off: bool, cfg_value: Option<&String>) -> bool {
+    if on || off {
+        return on
+    }
+    cfg_value.is_some_and(|v| v == "y")
+}
+
+impl ScanOptions {
+    pub fn new(args: &Args, cfg: &HashMap<String, HashMap<String, String>>) -> ScanOptions {
+        let scan_cfg = cfg.get("scan");
+        let cfg_value = |key: &str| scan_cfg.and_then(|section| section.get(key));
+
+        let max_depth = match args.max_depth {
+            Some(depth) => Some(depth as usize),
+            None if args.recursive => None,
+            None => Some(1),
+        };
+
+        ScanOptions {
+            max_depth,
+            follow_symlinks: args.follow_symlinks,
+            hidden: !resolve_flag(args.no_hidden, args.hidden, cfg_value("skip_hidden")),
+            ignore_files: resolve_flag(args.ignore_files, args.no_ignore_files, cfg_value("ignore_files")),
+        }
+    }
+}
+
+pub struct UploadFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub size: usize,
+    // Relative to the -d root the file was found under, empty otherwise.
+    pub rel_dir: PathBuf,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct UploadRecord {
+    pub host: String,
+    pub filename: String,
+    pub file_path: PathBuf,
+    pub uploaded_at: u64,
+    #[serde(flatten)]
+    pub result: UploadResult,
+}
+
+impl UploadRecord {
+    // The URL or the host's own ID for the upload. Other values like buckets and tokens are shared by many uploads, so they need an explicit key.
+    pub fn matches(&self, target: &str, key: Option<&str>) -> bool {
+        if let Some(key) = key {
+            return self.result.management.get(key).is_some_and(|v| v == target)
+        }
+        if self.result.url == target {
+            return true
+        }
+
+        let id_key = match HostArg::parse(&self.host) {
+            HostArg::Builtin(host) => host.id_key(),
+            HostArg::Custom(_) => None,
+        };
+        id_key.and_then(|k| self.result.management.get(k)).is_some_and(|v| v == target)
+    }
+}
+
+#[derive(Serialize)]
+pub struct FileResult {
+    pub host: String,
+    pub filename: String,
+    pub file_path: PathBuf,
+    // uploaded, failed or skipped.
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CollectionResult {
+    pub host: String,
+    pub url: String,
+}
+
+// Everything a run did, for the on_batch_done hook and webhook notifications.
+#[derive(Default, Serialize)]
+pub struct BatchSummary {
+    pub uploaded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub results: Vec<FileResult>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<CollectionResult>,
+}
+
+impl BatchSummary {
+    fn add_result(&mut self, file: &UploadFile, host: &HostArg, status: &'static str, url: Option<String>, error: Option<String>) {
+        self.results.push(FileResult {
+            host: host.to_string(),
+            filename: file.name.clone(),
+            file_path: file.path.clone(),
+            status,
+            url,
+            error,
+        });
+    }
+
+    pub fn add_uploaded(&mut self, record: &UploadRecord) {
+        self.uploaded += 1;
+        self.results.push(FileResult {
+            host: record.host.clone(),
+            filename: record.filename.clone(),
+            file_path: record.file_path.clone(),
+            status: "uploaded",
+            url: Some(record.result.url.clone()),
+            error: None,
+        });
+    }
+
+    pub fn add_failed(&mut self, file: &UploadFile, host: &HostArg, err: &dyn Error) {
+        self.failed += 1;
+        self.add_result(file, host, "failed", None, Some(err.to_string()));
+    }
+
+    pub fn add_skipped(&mut self, file: &UploadFile, host: &HostArg) {
+        self.skipped += 1;
+        self.add_result(file, host, "skipped", None, None);
+    }
+}
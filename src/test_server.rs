@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::{env, process, thread};
+use crate::structs::UploadFile;
+
+static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+// A fresh directory under the system temp dir, unique to this test run.
+pub fn temp_dir() -> PathBuf {
+    let n = TEMP_DIRS.fetch_add(1, Ordering::SeqCst);
+    let dir = env::temp_dir().join(format!("uploader-rs-test-{}-{}", process::id(), n));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Writes the file and describes it the way main does, uploaded under the given name.
+pub fn upload_file(file_name: &str, upload_name: &str, data: &[u8]) -> UploadFile {
+    let path = temp_dir().join(file_name);
+    fs::write(&path, data).unwrap();
+    UploadFile {
+        path,
+        name: upload_name.to_string(),
+        size: data.len(),
+        rel_dir: PathBuf::new(),
+    }
+}
+
+pub fn config(section: &str, values: &[(&str, &str)]) -> HashMap<String, HashMap<String, String>> {
+    let values = values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    HashMap::from([(section.to_string(), values)])
+}
+
+#[derive(Clone)]
+pub struct Request {
+    pub method: String,
+    // With the query, as sent.
+    pub path: String,
+    // Names are lowercased.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(|v| v.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+
+    // The address it was sent to, for replies that point back at the server.
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.header("Host").unwrap_or_default())
+    }
+}
+
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Response {
+        Response { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    pub fn text(status: u16, body: &str) -> Response {
+        Response::new(status).body(body.as_bytes())
+    }
+
+    pub fn json(status: u16, body: serde_json::Value) -> Response {
+        Response::text(status, &body.to_string()).header("Content-Type", "application/json")
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Response {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: &[u8]) -> Response {
+        self.body = body.to_vec();
+        self
+    }
+}
+
+// A stand-in http server on 127.0.0.1 that answers every request with the handler, and keeps what it was sent.
+pub struct TestServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl TestServer {
+    pub fn new<F>(handler: F) -> TestServer
+    where
+        F: Fn(&Request) -> Response + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests: Arc<Mutex<Vec<Request>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let server_requests = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue
+                };
+                let Some(request) = read_request(&stream) else {
+                    continue
+                };
+                server_requests.lock().unwrap().push(request.clone());
+                write_response(stream, handler(&request));
+            }
+        });
+        TestServer { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        self.requests().into_iter().map(|r| format!("{} {}", r.method, r.path)).collect()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers: HashMap<String, String> = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let mut body: Vec<u8> = Vec::new();
+    if headers.get("transfer-encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+        loop {
+            let mut size_line = String::new();
+            reader.read_line(&mut size_line).ok()?;
+            let size = usize::from_str_radix(size_line.trim(), 16).ok()?;
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else if let Some(len) = headers.get("content-length").and_then(|v| v.parse::<usize>().ok()) {
+        body.resize(len, 0);
+        reader.read_exact(&mut body).ok()?;
+    }
+
+    Some(Request { method, path, headers, body })
+}
+
+fn write_response(mut stream: TcpStream, response: Response) {
+    let mut head = format!("HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n", response.status, response.body.len());
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&response.body);
+}
@@ -1,9 +1,13 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::{env, fs};
 use std::io::{self, Error as IoError};
-use std::path::PathBuf;
-use crate::structs::Args;
+use std::path::{Path, PathBuf};
+use ignore::WalkBuilder;
+use crate::filters::{FileFilter, GlobFilter};
+use crate::structs::{Args, ScanOptions};
+
+const UPLOADER_IGNORE_FILENAME: &str = ".uploaderignore";
 
 pub fn get_exe_path() -> Result<PathBuf, Box<dyn Error>> {
     let exe_path = env::current_exe()?;
@@ -18,28 +22,68 @@ pub fn get_file_size(file_path: &PathBuf) -> io::Result<usize> {
     Ok(metadata.len() as usize)
 }
 
-pub fn get_fname_string_from_path(path: &PathBuf) -> Result<String, Box<dyn Error>> {
+pub fn get_fname_string_from_path(path: &Path) -> Result<String, Box<dyn Error>> {
     path.file_name()
         .map(|name| name.to_string_lossy().into_owned())
         .ok_or_else(|| "Path does not have a filename.".into())
 }
 
-fn populate_dirs(dir: &PathBuf, recursive: bool) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+// Unreadable directories and symlink loops are reported and skipped rather than failing the run.
+// Without follow_symlinks, symlinked files are still taken but symlinked directories aren't walked.
+pub fn populate_dirs(dir: &PathBuf, scan_opts: &ScanOptions) -> Vec<PathBuf> {
     let mut files: Vec<PathBuf> = Vec::new();
 
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let path = entry?.path();
-            if path.is_dir() && recursive {
-                let child_dirs = populate_dirs(&path, true)?;
-                files.extend(child_dirs);
-            } else if path.is_file() {
-                files.push(path.clone());
-            }
+    let mut walker = WalkBuilder::new(dir);
+    walker
+        .standard_filters(false)
+        .hidden(!scan_opts.hidden)
+        .max_depth(scan_opts.max_depth)
+        // Loops are caught by comparing each directory against its ancestors by device and inode.
+        .follow_links(scan_opts.follow_symlinks)
+        .sort_by_file_name(|a, b| a.cmp(b));
+
+    // Ignore files are honoured outside of git repos too, since copies of a project are often uploaded.
+    if scan_opts.ignore_files {
+        walker
+            .parents(true)
+            .ignore(true)
+            .git_ignore(true)
+            .git_global(true)
+            .git_exclude(true)
+            .require_git(false)
+            .add_custom_ignore_filename(UPLOADER_IGNORE_FILENAME);
+    }
+
+    let mut skipped_symlinks = 0;
+    for entry in walker.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                println!("Skipped during scan: {}", err);
+                continue;
+            },
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+
+        match entry.file_type() {
+            Some(file_type) if file_type.is_file() => files.push(entry.into_path()),
+            Some(file_type) if file_type.is_symlink() => {
+                if entry.path().is_file() {
+                    files.push(entry.into_path());
+                } else if entry.path().is_dir() {
+                    skipped_symlinks += 1;
+                }
+            },
+            _ => {},
         }
     }
 
-    Ok(files)
+    if skipped_symlinks > 0 {
+        println!("Skipped {} symlinked folder(s) in {}, use --follow-symlinks to scan them.", skipped_symlinks, dir.display());
+    }
+    files
 }
 
 fn check_exists(file_path: &PathBuf, is_dir: bool) -> Result<bool, IoError> {
@@ -57,7 +101,7 @@ fn check_exists(file_path: &PathBuf, is_dir: bool) -> Result<bool, IoError> {
     }
 }
 
-pub fn process_dirs(args: &mut Args) -> Result<(), Box<dyn Error>> {
+pub fn process_dirs(args: &mut Args, scan_opts: &ScanOptions, glob_filter: &mut GlobFilter) -> Result<(), Box<dyn Error>> {
     let mut all_dirs: HashSet<PathBuf> = HashSet::new();
     let mut pop_paths: Vec<PathBuf>;
 
@@ -68,7 +112,8 @@ pub fn process_dirs(args: &mut Args) -> Result<(), Box<dyn Error>> {
             if !all_dirs.contains(dir) {
                 all_dirs.insert(dir.clone());
 
-                pop_paths = populate_dirs(dir, args.recursive)?;
+                pop_paths = populate_dirs(dir, scan_opts);
+                pop_paths.retain(|p| glob_filter.keep(p.strip_prefix(dir).unwrap_or(p)));
 
                 args.file_paths.extend(pop_paths);
             } else {
@@ -78,18 +123,19 @@ pub fn process_dirs(args: &mut Args) -> Result<(), Box<dyn Error>> {
             println!("Filtered non-existent directory: {}", dir.display());
         }
     }
+    glob_filter.print_report();
 
     Ok(())
 }
 
 #[cfg(target_os = "windows")]
-pub fn normalise_path_case(path: &PathBuf) -> Result<PathBuf, io::Error> {
+pub fn normalise_path_case(path: &Path) -> Result<PathBuf, io::Error> {
     let canon_path = fs::canonicalize(path)?;
     Ok(canon_path)
 }
 
 #[cfg(not(target_os = "windows"))]
-pub fn normalise_path_case(path: &PathBuf) -> Result<PathBuf, io::Error> {
+pub fn normalise_path_case(path: &Path) -> Result<PathBuf, io::Error> {
     Ok(path.to_path_buf())
 }
 
@@ -99,7 +145,48 @@ pub fn get_abs_path(path: &PathBuf) -> io::Result<PathBuf> {
     Ok(abs_path)
 }
 
-pub fn filter_paths(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, io::Error> {
+pub fn normalise_dirs(dirs: &[PathBuf]) -> Result<Vec<PathBuf>, io::Error> {
+    let mut norm_dirs: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        let abs_dir = if dir.is_absolute() {
+            dir.clone()
+        } else {
+            get_abs_path(dir)?
+        };
+
+        // Non-existent dirs were already reported by process_dirs.
+        if let Ok(norm_dir) = normalise_path_case(&abs_dir) {
+            norm_dirs.push(norm_dir);
+        }
+    }
+    Ok(norm_dirs)
+}
+
+// Directory of the file relative to the deepest scanned dir containing it.
+pub fn get_rel_dir(path: &Path, dirs: &[PathBuf]) -> PathBuf {
+    let parent = match path.parent() {
+        Some(parent) => parent,
+        None => return PathBuf::new(),
+    };
+
+    dirs.iter()
+        .filter_map(|dir| parent.strip_prefix(dir).ok())
+        .min_by_key(|rel| rel.components().count())
+        .map(PathBuf::from)
+        .unwrap_or_default()
+}
+
+// Absolute, and on Windows canonical so differently cased paths to the same file match.
+pub fn get_norm_path(path: &PathBuf) -> io::Result<PathBuf> {
+    let abs_path = if path.is_absolute() {
+        path.clone()
+    } else {
+        get_abs_path(path)?
+    };
+    normalise_path_case(&abs_path)
+}
+
+pub fn filter_paths(paths: Vec<PathBuf>, file_filter: &mut FileFilter) -> Result<Vec<PathBuf>, io::Error> {
     let mut filtered_paths: Vec<PathBuf> = Vec::new();
 
     let mut seen_paths: HashSet<PathBuf> = HashSet::new();
@@ -118,7 +205,9 @@ pub fn filter_paths(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, io::Error> {
             };
             if !seen_paths.contains(&norm_path) {
                 seen_paths.insert(norm_path.clone());
-                filtered_paths.push(norm_path);
+                if file_filter.keep(&norm_path)? {
+                    filtered_paths.push(norm_path);
+                }
             } else {
                 println!("Filtered duplicate file: {:?}", path.display());
             }
@@ -126,15 +215,31 @@ pub fn filter_paths(paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, io::Error> {
             println!("Filtered non-existent file: {:?}", path.display());
         }
     }
+    file_filter.print_report();
     Ok(filtered_paths)
 }
 
-pub fn print_path_no_prefix(path: &PathBuf) {
+pub fn print_path_no_prefix(path: &Path) {
     let p = path.to_string_lossy().to_string();
-    let trimmed_path = if p.starts_with(r"\\?\") {
-        &p[4..]
-    } else {
-        &p
-    };
+    let trimmed_path = p.strip_prefix(r"\\?\").unwrap_or(&p);
     println!("{}", trimmed_path);
-}
\ No newline at end of file
+}
+
+fn set_config_override(cfg: &mut HashMap<String, HashMap<String, String>>, section: &str, key: &str, value: Option<String>) {
+    if let Some(value) = value {
+        cfg.entry(section.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+    }
+}
+
+pub fn apply_config_overrides(args: &Args, cfg: &mut HashMap<String, HashMap<String, String>>) {
+    let auto_delete = args.fileio_auto_delete
+        .map(|v| if v { "y" } else { "n" }.to_string());
+
+    set_config_override(cfg, "fileio", "api_key", args.fileio_api_key.clone());
+    set_config_override(cfg, "fileio", "auto_delete", auto_delete);
+    set_config_override(cfg, "fileio", "expires", args.fileio_expires.clone());
+    set_config_override(cfg, "fileio", "max_downloads", args.fileio_max_downloads.map(|v| v.to_string()));
+    set_config_override(cfg, "litterbox", "time", args.litterbox_time.clone());
+}
@@ -0,0 +1,536 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use notify::event::{AccessKind, AccessMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use crate::actions::{self, LocalAction};
+use crate::client::UploaderClient;
+use crate::filters::{FileFilter, GlobFilter};
+use crate::hooks::Hooks;
+use crate::hosts::enums::Host;
+use crate::structs::{Args, BatchSummary, Command, ScanOptions};
+use crate::utils;
+use crate::webhooks::Notifier;
+
+const JOURNAL_FILENAME: &str = "watch_journal.jsonl";
+const TICK: Duration = Duration::from_secs(1);
+const RETRY_BASE_DELAY_SECS: u64 = 30;
+const RETRY_MAX_DELAY_SECS: u64 = 600;
+
+// A version of a file, so a file replaced under the same name is uploaded again.
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+struct FileKey {
+    path: PathBuf,
+    size: u64,
+    mtime: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct JournalEntry {
+    #[serde(flatten)]
+    key: FileKey,
+    host: String,
+}
+
+// Which hosts each file already went to, appended to as uploads finish so a restart carries on where it stopped.
+struct Journal {
+    path: PathBuf,
+    done: HashMap<FileKey, HashSet<String>>,
+}
+
+impl Journal {
+    fn load(path: &Path) -> Result<Journal, Box<dyn Error>> {
+        let mut journal = Journal {
+            path: path.to_path_buf(),
+            done: HashMap::new(),
+        };
+
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(journal),
+            Err(err) => return Err(err.into()),
+        };
+        for line in data.lines().filter(|l| !l.trim().is_empty()) {
+            let entry: JournalEntry = serde_json::from_str(line)?;
+            journal.done.entry(entry.key).or_default().insert(entry.host);
+        }
+        Ok(journal)
+    }
+
+    fn is_done(&self, key: &FileKey, host: &str) -> bool {
+        self.done.get(key).is_some_and(|hosts| hosts.contains(host))
+    }
+
+    fn add(&mut self, key: &FileKey, host: &str) -> Result<(), Box<dyn Error>> {
+        let entry = JournalEntry {
+            key: key.clone(),
+            host: host.to_string(),
+        };
+        let mut data = serde_json::to_vec(&entry)?;
+        data.push(b'\n');
+
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        f.write_all(&data)?;
+
+        self.done.entry(entry.key).or_default().insert(entry.host);
+        Ok(())
+    }
+}
+
+struct Pending {
+    size: u64,
+    mtime: SystemTime,
+    changed_at: Instant,
+    // Closed after writing, so there's no need to wait for the settle period.
+    closed: bool,
+}
+
+// The file went away or couldn't be read. Failing to write outputs after an upload is something else.
+#[derive(Debug)]
+struct SourceError(Box<dyn Error>);
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "couldn't read the file: {}", self.0)
+    }
+}
+
+impl Error for SourceError {}
+
+struct Retry {
+    attempts: u32,
+    next_try: Instant,
+}
+
+fn get_file_key(path: &Path) -> io::Result<FileKey> {
+    let meta = fs::metadata(path)?;
+    let mtime = meta.modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let key = FileKey {
+        path: path.to_path_buf(),
+        size: meta.len(),
+        mtime,
+    };
+    Ok(key)
+}
+
+fn mark_changed(pending: &mut HashMap<PathBuf, Pending>, path: &Path, closed: bool) {
+    let entry = pending.entry(path.to_path_buf()).or_insert_with(|| Pending {
+        size: 0,
+        mtime: UNIX_EPOCH,
+        changed_at: Instant::now(),
+        closed: false,
+    });
+    entry.changed_at = Instant::now();
+    entry.closed = closed;
+}
+
+fn handle_event(pending: &mut HashMap<PathBuf, Pending>, scan_opts: &ScanOptions, event: Event) {
+    for path in &event.paths {
+        match event.kind {
+            EventKind::Create(_) | EventKind::Modify(_) => {
+                // Directories moved in don't get events for the files inside them.
+                if path.is_dir() {
+                    for p in utils::populate_dirs(path, scan_opts) {
+                        mark_changed(pending, &p, false);
+                    }
+                } else {
+                    mark_changed(pending, path, false);
+                }
+            },
+            EventKind::Access(AccessKind::Close(AccessMode::Write)) => mark_changed(pending, path, true),
+            EventKind::Remove(_) => {
+                pending.remove(path);
+            },
+            _ => {},
+        }
+    }
+}
+
+// Files that were closed after writing, or haven't changed for the settle period.
+fn take_ready(pending: &mut HashMap<PathBuf, Pending>, settle: Duration) -> Vec<PathBuf> {
+    let mut ready: Vec<PathBuf> = Vec::new();
+    pending.retain(|path, p| {
+        let meta = match fs::metadata(path) {
+            Ok(meta) if meta.is_file() => meta,
+            _ => return false,
+        };
+        let mtime = meta.modified().unwrap_or(UNIX_EPOCH);
+
+        if meta.len() != p.size || mtime != p.mtime {
+            p.size = meta.len();
+            p.mtime = mtime;
+            if !p.closed {
+                p.changed_at = Instant::now();
+            }
+        }
+
+        if p.closed || p.changed_at.elapsed() >= settle {
+            ready.push(path.clone());
+            return false
+        }
+        true
+    });
+    ready
+}
+
+struct Scanner<'a> {
+    args: &'a Args,
+    scan_opts: ScanOptions,
+    glob_filter: GlobFilter,
+    file_filter: FileFilter,
+    move_dirs: Vec<PathBuf>,
+}
+
+impl Scanner<'_> {
+    // The same checks a -d scan does. Ignore files are only read by the startup scan.
+    fn is_wanted(&mut self, path: &Path) -> bool {
+        if self.move_dirs.iter().any(|dir| path.starts_with(dir)) {
+            return false
+        }
+
+        let Some((root, rel_path)) = self.args.directories.iter()
+            .filter_map(|dir| path.strip_prefix(dir).ok().map(|rel| (dir, rel)))
+            .min_by_key(|(_, rel)| rel.components().count()) else {
+            return false
+        };
+
+        if self.scan_opts.max_depth.is_some_and(|depth| rel_path.components().count() > depth) {
+            return false
+        }
+        if !self.scan_opts.hidden && rel_path.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')) {
+            return false
+        }
+        // Symlinked files are taken, files reached through a symlinked directory aren't.
+        if !self.scan_opts.follow_symlinks && rel_path.ancestors()
+            .skip(1)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .any(|dir| fs::symlink_metadata(root.join(dir)).is_ok_and(|m| m.file_type().is_symlink())) {
+            return false
+        }
+
+        self.glob_filter.keep(rel_path) && self.file_filter.keep(path).unwrap_or(false)
+    }
+}
+
+fn get_retry_delay(attempts: u32) -> Duration {
+    let secs = RETRY_BASE_DELAY_SECS.saturating_mul(1 << attempts.saturating_sub(1).min(16));
+    Duration::from_secs(secs.min(RETRY_MAX_DELAY_SECS))
+}
+
+struct Uploader<'a> {
+    args: &'a Args,
+    cfg: &'a HashMap<String, HashMap<String, String>>,
+    client: &'a mut UploaderClient,
+    sessions: &'a mut HashMap<String, HashMap<String, String>>,
+    history_path: &'a PathBuf,
+    hooks: &'a Hooks,
+    journal: Journal,
+    func_map: HashMap<Host, crate::RunFuncType>,
+    batch_func_map: HashMap<Host, crate::BatchFuncType>,
+}
+
+// Uploads a file to every host that doesn't have it yet. Returns whether any host failed, and whether the pre_upload hook skipped any.
+fn upload_file(u: &mut Uploader, summary: &mut BatchSummary, path: &PathBuf) -> Result<(bool, bool), Box<dyn Error>> {
+    let Uploader { args, cfg, hooks, history_path, .. } = *u;
+    let key = get_file_key(path).map_err(|e| SourceError(e.into()))?;
+
+    let mut failed = false;
+    let mut skipped = false;
+    for host in &args.hosts {
+        if u.journal.is_done(&key, &host.to_string()) {
+            continue;
+        }
+        println!("{}:", host);
+        utils::print_path_no_prefix(path);
+
+        let file = crate::make_upload_file(args, path).map_err(SourceError)?;
+        if !hooks.pre_upload(&file, host) {
+            println!("Skipped by pre_upload hook.");
+            summary.add_skipped(&file, host);
+            skipped = true;
+            continue;
+        }
+        // The hook may have changed the file, it's uploaded as it is now.
+        let file = crate::make_upload_file(args, path).map_err(SourceError)?;
+
+        let result = match crate::upload_to_host(u.client, &u.func_map, host, &file, cfg, u.sessions) {
+            Ok(result) => result,
+            Err(err) => {
+                println!("Upload failed.\n{:?}", err);
+                hooks.on_failure(&file, host, err.as_ref());
+                summary.add_failed(&file, host, err.as_ref());
+                if crate::is_fatal_error(err.as_ref()) {
+                    return Err(err)
+                }
+                failed = true;
+                continue;
+            },
+        };
+
+        if let Some(LocalAction::Sidecar) = args.on_success {
+            if let Err(err) = actions::write_sidecar(&file, host, args.hosts.len(), &result.url) {
+                println!("Failed to write sidecar.\n{:?}", err);
+            }
+        }
+        // The upload happened either way, so it's journaled even if the outputs couldn't be written.
+        match crate::write_outputs(args, host, file, result, history_path) {
+            Ok(record) => {
+                hooks.post_upload(&record);
+                summary.add_uploaded(&record);
+            },
+            Err(err) => println!("Failed to write outputs.\n{:?}", err),
+        }
+        // Carrying on without the journal would upload everything again after a restart.
+        u.journal.add(&key, &host.to_string())
+            .map_err(|e| format!("failed to write to the journal: {}", e))?;
+    }
+    Ok((failed, skipped))
+}
+
+pub fn run(args: &Args, cfg: &HashMap<String, HashMap<String, String>>, c: &mut UploaderClient, s: &mut HashMap<String, HashMap<String, String>>, history_path: &PathBuf, hooks: &Hooks, notifier: &Notifier) -> Result<(), Box<dyn Error>> {
+    let Some(Command::Watch { journal, retries, settle, .. }) = &args.command else {
+        return Err("not in watch mode".into())
+    };
+    let settle = Duration::from_secs(*settle);
+
+    let journal_path = match journal {
+        Some(path) => path.clone(),
+        None => utils::get_exe_path()?.join(JOURNAL_FILENAME),
+    };
+    let journal = Journal::load(&journal_path)?;
+
+    // Moved files would be picked up again if the target is inside a watched directory.
+    let mut move_dirs: Vec<PathBuf> = Vec::new();
+    for dir in [&args.on_success, &args.on_failure].into_iter().flatten().filter_map(LocalAction::move_dir) {
+        fs::create_dir_all(dir)?;
+        move_dirs.push(utils::get_norm_path(dir)?);
+    }
+
+    let mut scanner = Scanner {
+        args,
+        scan_opts: ScanOptions::new(args, cfg),
+        glob_filter: GlobFilter::new(&args.include, &args.exclude, cfg)?,
+        file_filter: FileFilter::new(args),
+        move_dirs,
+    };
+
+    let mut uploader = Uploader {
+        args,
+        cfg,
+        client: c,
+        sessions: s,
+        history_path,
+        hooks,
+        journal,
+        func_map: crate::make_func_map(),
+        batch_func_map: crate::make_batch_func_map(),
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let mode = if scanner.scan_opts.max_depth == Some(1) { RecursiveMode::NonRecursive } else { RecursiveMode::Recursive };
+    for dir in &args.directories {
+        watcher.watch(dir, mode)?;
+        println!("Watching {}", dir.display());
+    }
+
+    // Files already there are uploaded too, unless the journal has them.
+    let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+    for path in &args.file_paths {
+        mark_changed(&mut pending, path, false);
+    }
+
+    let mut retry_queue: HashMap<PathBuf, Retry> = HashMap::new();
+    let mut last_check = Instant::now();
+    loop {
+        match rx.recv_timeout(TICK) {
+            Ok(Ok(event)) => handle_event(&mut pending, &scanner.scan_opts, event),
+            Ok(Err(err)) => println!("Watch error.\n{:?}", err),
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => return Err("directory watcher stopped".into()),
+        }
+        if last_check.elapsed() < TICK {
+            continue;
+        }
+        last_check = Instant::now();
+
+        let mut ready = take_ready(&mut pending, settle);
+        for (path, r) in &retry_queue {
+            if r.next_try <= Instant::now() && !ready.contains(path) {
+                ready.push(path.clone());
+            }
+        }
+        ready.sort();
+        if ready.is_empty() {
+            continue;
+        }
+
+        // Each round of ready files is a batch for the on_batch_done hook.
+        let mut summary = BatchSummary::default();
+        for path in ready {
+            if !scanner.is_wanted(&path) {
+                retry_queue.remove(&path);
+                continue;
+            }
+
+            let (failed, skipped) = match upload_file(&mut uploader, &mut summary, &path) {
+                Ok(outcome) => outcome,
+                // The file went away or couldn't be read, any retry is dropped with it.
+                Err(err) if err.downcast_ref::<SourceError>().is_some() => {
+                    println!("Skipped {}.\n{:?}", path.display(), err);
+                    retry_queue.remove(&path);
+                    continue;
+                },
+                Err(err) => return Err(err),
+            };
+
+            // Skipped hosts aren't retried, and the file isn't done with either.
+            if !failed {
+                retry_queue.remove(&path);
+                if !skipped {
+                    crate::apply_local_action(args, &path, false);
+                }
+                continue;
+            }
+
+            let r = retry_queue.entry(path.clone()).or_insert(Retry { attempts: 0, next_try: Instant::now() });
+            r.attempts += 1;
+            if r.attempts > *retries {
+                println!("Giving up on {} after {} retries.", path.display(), retries);
+                retry_queue.remove(&path);
+                crate::apply_local_action(args, &path, true);
+            } else {
+                let delay = get_retry_delay(r.attempts);
+                r.next_try = Instant::now() + delay;
+                println!("Retrying in {}s.", delay.as_secs());
+            }
+        }
+        // Rounds where every file was filtered out did nothing worth reporting.
+        if !summary.results.is_empty() {
+            // Collections are per round too, which also keeps host batch state from piling up between rounds.
+            for host in &args.hosts {
+                crate::finish_batch(args, uploader.client, &uploader.batch_func_map, host, cfg, uploader.sessions, &mut summary)?;
+            }
+            hooks.on_batch_done(&summary);
+            notifier.send(uploader.client, &summary);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_server;
+    use super::*;
+
+    #[test]
+    fn journal_survives_restart() {
+        let dir = test_server::temp_dir();
+        let journal_path = dir.join(JOURNAL_FILENAME);
+        let file_path = dir.join("a.txt");
+        fs::write(&file_path, "hello").unwrap();
+        let key = get_file_key(&file_path).unwrap();
+
+        let mut journal = Journal::load(&journal_path).unwrap();
+        assert!(!journal.is_done(&key, "gofile"));
+        journal.add(&key, "gofile").unwrap();
+        assert!(journal.is_done(&key, "gofile"));
+        assert!(!journal.is_done(&key, "catbox"));
+
+        let journal = Journal::load(&journal_path).unwrap();
+        assert!(journal.is_done(&key, "gofile"));
+        assert!(!journal.is_done(&key, "catbox"));
+
+        // The same name with other contents is a new file.
+        let changed = FileKey { size: 6, ..key.clone() };
+        assert!(!journal.is_done(&changed, "gofile"));
+    }
+
+    #[test]
+    fn journal_appends() {
+        let dir = test_server::temp_dir();
+        let journal_path = dir.join(JOURNAL_FILENAME);
+        let key = FileKey { path: dir.join("a.txt"), size: 5, mtime: 1700000000 };
+
+        Journal::load(&journal_path).unwrap().add(&key, "gofile").unwrap();
+        let mut journal = Journal::load(&journal_path).unwrap();
+        journal.add(&key, "catbox").unwrap();
+        fs::OpenOptions::new().append(true).open(&journal_path).unwrap().write_all(b"\n").unwrap();
+
+        let journal = Journal::load(&journal_path).unwrap();
+        assert!(journal.is_done(&key, "gofile"));
+        assert!(journal.is_done(&key, "catbox"));
+        assert_eq!(fs::read_to_string(&journal_path).unwrap().lines().filter(|l| !l.is_empty()).count(), 2);
+    }
+
+    #[test]
+    fn bad_journal() {
+        let journal_path = test_server::temp_dir().join(JOURNAL_FILENAME);
+        fs::write(&journal_path, "not json\n").unwrap();
+        assert!(Journal::load(&journal_path).is_err());
+    }
+
+    #[test]
+    fn settling() {
+        let dir = test_server::temp_dir();
+        let path = dir.join("a.txt");
+        fs::write(&path, "hello").unwrap();
+
+        let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+        mark_changed(&mut pending, &path, false);
+        assert!(take_ready(&mut pending, Duration::from_secs(60)).is_empty());
+        assert!(pending.contains_key(&path));
+
+        // Unchanged for the settle period.
+        pending.get_mut(&path).unwrap().changed_at = Instant::now() - Duration::from_secs(61);
+        assert_eq!(take_ready(&mut pending, Duration::from_secs(60)), std::slice::from_ref(&path));
+        assert!(pending.is_empty());
+
+        // Still being written, the wait starts over.
+        mark_changed(&mut pending, &path, false);
+        take_ready(&mut pending, Duration::from_secs(60));
+        pending.get_mut(&path).unwrap().changed_at = Instant::now() - Duration::from_secs(61);
+        fs::write(&path, "hello again").unwrap();
+        assert!(take_ready(&mut pending, Duration::from_secs(60)).is_empty());
+        assert!(pending.contains_key(&path));
+    }
+
+    #[test]
+    fn closed_files_skip_settling() {
+        let dir = test_server::temp_dir();
+        let path = dir.join("a.txt");
+        fs::write(&path, "hello").unwrap();
+
+        let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+        mark_changed(&mut pending, &path, true);
+        assert_eq!(take_ready(&mut pending, Duration::from_secs(60)), [path]);
+    }
+
+    #[test]
+    fn gone_files_are_dropped() {
+        let dir = test_server::temp_dir();
+        let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+        mark_changed(&mut pending, &dir.join("missing.txt"), true);
+        mark_changed(&mut pending, &dir, true);
+        assert!(take_ready(&mut pending, Duration::ZERO).is_empty());
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn retry_delays() {
+        let delays: Vec<u64> = (1..=7).map(|n| get_retry_delay(n).as_secs()).collect();
+        assert_eq!(delays, [30, 60, 120, 240, 480, 600, 600]);
+        assert_eq!(get_retry_delay(u32::MAX).as_secs(), RETRY_MAX_DELAY_SECS);
+    }
+}
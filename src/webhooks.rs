@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+use serde_json::json;
+use url::Url;
+use crate::client::UploaderClient;
+use crate::structs::BatchSummary;
+
+const NOTIFY_SECTION: &str = "notify";
+const DEFAULT_MESSAGE_TEMPLATE: &str = "Uploaded <uploaded> file(s), <failed> failed.<newline><links>";
+const DEFAULT_RETRIES: u32 = 3;
+const RETRY_BASE_DELAY_SECS: u64 = 2;
+const DISCORD_MAX_LEN: usize = 2000;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Discord,
+    Slack,
+    Matrix,
+}
+
+const FORMAT_KEYS: [(&str, Format); 4] = [
+    ("json_urls", Format::Json),
+    ("discord_urls", Format::Discord),
+    ("slack_urls", Format::Slack),
+    ("matrix_urls", Format::Matrix),
+];
+
+// Webhooks from the [notify] section, POSTed to once a batch is done.
+pub struct Notifier {
+    targets: Vec<(Format, String)>,
+    message_template: String,
+    retries: u32,
+}
+
+fn get_links(summary: &BatchSummary) -> String {
+    let uploads = summary.results.iter()
+        .filter_map(|r| r.url.as_ref().map(|url| format!("{} ({}): {}", r.filename, r.host, url)));
+    let collections = summary.collections.iter()
+        .map(|c| format!("Collection ({}): {}", c.host, c.url));
+    uploads.chain(collections).collect::<Vec<String>>().join("\n")
+}
+
+// Discord rejects longer messages, so they're split between lines into several posts. Lines too long for one post are cut up.
+fn split_message(message: &str, max_len: usize) -> Vec<String> {
+    let pieces = message.split('\n').flat_map(|line| {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            vec![String::new()]
+        } else {
+            chars.chunks(max_len).map(|chunk| chunk.iter().collect()).collect()
+        }
+    });
+
+    let mut parts: Vec<(String, usize)> = Vec::new();
+    for piece in pieces {
+        let len = piece.chars().count();
+        match parts.last_mut() {
+            Some((part, part_len)) if *part_len + 1 + len <= max_len => {
+                part.push('\n');
+                part.push_str(&piece);
+                *part_len += 1 + len;
+            },
+            _ => parts.push((piece, len)),
+        }
+    }
+    parts.into_iter()
+        .map(|(part, _)| part)
+        .filter(|part| !part.trim().is_empty())
+        .collect()
+}
+
+// Webhook urls carry their token in the path or query, so only the origin is printed.
+fn redact_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(_) => "an invalid url".to_string(),
+    }
+}
+
+// reqwest errors include the url they were for.
+fn strip_url(err: Box<dyn Error>) -> Box<dyn Error> {
+    match err.downcast::<reqwest::Error>() {
+        Ok(err) => Box::new(err.without_url()),
+        Err(err) => err,
+    }
+}
+
+// Connection errors, rate limits and server errors are worth another try, other statuses won't change.
+fn is_retryable(err: &(dyn Error + 'static)) -> bool {
+    match err.downcast_ref::<reqwest::Error>() {
+        Some(err) => err.status().is_none_or(|s| s.is_server_error() || s.as_u16() == 429),
+        None => false,
+    }
+}
+
+impl Notifier {
+    pub fn new(cfg: &HashMap<String, HashMap<String, String>>) -> Result<Notifier, Box<dyn Error>> {
+        let empty = HashMap::new();
+        let section = cfg.get(NOTIFY_SECTION).unwrap_or(&empty);
+        let get_value = |key: &str| section.get(key).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+        let mut targets: Vec<(Format, String)> = Vec::new();
+        for (key, format) in FORMAT_KEYS {
+            if let Some(urls) = get_value(key) {
+                targets.extend(urls.split(',')
+                    .map(|url| url.trim())
+                    .filter(|url| !url.is_empty())
+                    .map(|url| (format, url.to_string())));
+            }
+        }
+
+        let retries = match get_value("retries") {
+            Some(retries) => retries.parse::<u32>()
+                .map_err(|_| format!("invalid [notify] retries: {}", retries))?,
+            None => DEFAULT_RETRIES,
+        };
+
+        let notifier = Notifier {
+            targets,
+            message_template: get_value("message_template").unwrap_or(DEFAULT_MESSAGE_TEMPLATE).to_string(),
+            retries,
+        };
+        Ok(notifier)
+    }
+
+    fn make_message(&self, summary: &BatchSummary) -> String {
+        let vars = [
+            ("uploaded", summary.uploaded.to_string()),
+            ("failed", summary.failed.to_string()),
+            ("skipped", summary.skipped.to_string()),
+            ("links", get_links(summary)),
+            ("newline", "\n".to_string()),
+        ];
+        let mut message = self.message_template.clone();
+        for (name, value) in &vars {
+            message = message.replace(&format!("<{}>", name), value);
+        }
+        message.trim_end().to_string()
+    }
+
+    fn make_payloads(&self, format: Format, summary: &BatchSummary) -> Result<Vec<serde_json::Value>, Box<dyn Error>> {
+        let payloads = match format {
+            Format::Json => vec![serde_json::to_value(summary)?],
+            Format::Discord => split_message(&self.make_message(summary), DISCORD_MAX_LEN)
+                .into_iter()
+                .map(|content| json!({ "content": content }))
+                .collect(),
+            // Matrix webhook bridges like hookshot take the same shape as Slack.
+            Format::Slack | Format::Matrix => vec![json!({ "text": self.make_message(summary) })],
+        };
+        Ok(payloads)
+    }
+
+    fn post(&self, c: &UploaderClient, url: &str, payload: &serde_json::Value) -> Result<(), Box<dyn Error>> {
+        let mut attempts = 0;
+        loop {
+            let err = match c.do_post(url, None, Some(payload)) {
+                Ok(_) => return Ok(()),
+                Err(err) => strip_url(err),
+            };
+            if attempts >= self.retries || !is_retryable(err.as_ref()) {
+                return Err(err)
+            }
+
+            attempts += 1;
+            let delay = RETRY_BASE_DELAY_SECS << (attempts - 1).min(8);
+            println!("Notification failed, retrying in {}s.\n{:?}", delay, err);
+            thread::sleep(Duration::from_secs(delay));
+        }
+    }
+
+    pub fn send(&self, c: &UploaderClient, summary: &BatchSummary) {
+        for (format, url) in &self.targets {
+            let result = self.make_payloads(*format, summary)
+                .and_then(|payloads| payloads.iter().try_for_each(|payload| self.post(c, url, payload)));
+            if let Err(err) = result {
+                println!("Failed to send notification to a webhook at {}.\n{:?}", redact_url(url), err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use crate::structs::FileResult;
+    use super::*;
+
+    // Answers each request with the next status, and returns the path and JSON body of every request.
+    fn serve(listener: TcpListener, statuses: Vec<u16>) -> thread::JoinHandle<Vec<(String, serde_json::Value)>> {
+        thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split(' ').nth(1).unwrap().to_string();
+
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                requests.push((path, serde_json::from_slice(&body).unwrap()));
+
+                let response = format!("HTTP/1.1 {} Test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                reader.into_inner().write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        })
+    }
+
+    fn make_summary(uploads: usize) -> BatchSummary {
+        let results = (0..uploads).map(|i| FileResult {
+            host: "gofile".to_string(),
+            filename: format!("file{}.bin", i),
+            file_path: PathBuf::from(format!("file{}.bin", i)),
+            status: "uploaded",
+            url: Some(format!("https://gofile.io/d/{:040}", i)),
+            error: None,
+        }).collect();
+        BatchSummary { uploaded: uploads, results, ..Default::default() }
+    }
+
+    fn make_notifier(base: &str, retries: &str) -> Notifier {
+        let section: HashMap<String, String> = [
+            ("json_urls", format!("{}/json?token=secret", base)),
+            ("discord_urls", format!("{}/discord/secret", base)),
+            ("slack_urls", format!("{}/slack/secret", base)),
+            ("retries", retries.to_string()),
+        ].into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+        Notifier::new(&HashMap::from([(NOTIFY_SECTION.to_string(), section)])).unwrap()
+    }
+
+    #[test]
+    fn payload_shapes_and_retries() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        // The JSON webhook fails once, the long Discord message takes two posts.
+        let server = serve(listener, vec![503, 200, 204, 204, 200]);
+
+        let summary = make_summary(40);
+        make_notifier(&base, "1").send(&UploaderClient::new().unwrap(), &summary);
+        let requests = server.join().unwrap();
+        let paths: Vec<&str> = requests.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["/json?token=secret", "/json?token=secret", "/discord/secret", "/discord/secret", "/slack/secret"]);
+
+        let json = &requests[1].1;
+        assert_eq!(json, &requests[0].1);
+        assert_eq!(json["uploaded"], 40);
+        assert_eq!(json["results"].as_array().unwrap().len(), 40);
+        assert_eq!(json["results"][0]["status"], "uploaded");
+
+        // Discord gets every link, split between lines.
+        let discord: Vec<&str> = requests[2..4].iter().map(|(_, body)| body["content"].as_str().unwrap()).collect();
+        assert!(discord.iter().all(|content| content.chars().count() <= DISCORD_MAX_LEN));
+        assert!(discord[0].starts_with("Uploaded 40 file(s), 0 failed.\nfile0.bin (gofile): "));
+        assert!(discord[1].starts_with("file"));
+        assert_eq!(discord.join("\n"), requests[4].1["text"].as_str().unwrap());
+        assert!(requests[4].1.get("content").is_none());
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = serve(listener, vec![400, 404, 200]);
+
+        make_notifier(&base, "3").send(&UploaderClient::new().unwrap(), &make_summary(1));
+        let paths: Vec<String> = server.join().unwrap().into_iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, ["/json?token=secret", "/discord/secret", "/slack/secret"]);
+    }
+
+    #[test]
+    fn split_long_messages() {
+        assert_eq!(split_message("a\nbb\nccc", 4), ["a\nbb", "ccc"]);
+        assert_eq!(split_message("abcdefghij\nk", 4), ["abcd", "efgh", "ij\nk"]);
+        assert_eq!(split_message("héllo", 10), ["héllo"]);
+        assert_eq!(split_message("aaaa\n\nbbbb", 4), ["aaaa", "bbbb"]);
+        assert!(split_message("", 4).is_empty());
+    }
+
+    #[test]
+    fn urls_are_redacted() {
+        assert_eq!(redact_url("https://discord.com/api/webhooks/123/token"), "https://discord.com");
+        assert_eq!(redact_url("http://127.0.0.1:9000/hook?key=secret"), "http://127.0.0.1:9000");
+        assert_eq!(redact_url("not a url"), "an invalid url");
+    }
+}